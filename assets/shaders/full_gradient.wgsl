@@ -3,16 +3,32 @@
     utils::coords_to_viewport_uv,
 }
 #import bevy_pbr::mesh_view_bindings::globals;
-#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip}
+#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip, get_tag}
 
-#import "bevy_sky_gradient/shaders/gradient.wgsl"::{GradientSettings, gradient};
+#import "bevy_sky_gradient/shaders/gradient.wgsl"::{GradientStop, Ground, gradient_t, ground_t, ground_blend, mix_stops};
 
+// sorted by position, never empty
 @group(#{MATERIAL_BIND_GROUP}) @binding(0)
-var<uniform> gradient_settings: GradientSettings;
+var<storage, read> gradient_stops: array<GradientStop>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1)
+var<uniform> feature_lut_enabled: i32;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2)
+var lut_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(3)
+var lut_texture_sampler: sampler;
+// sorted by position, never empty. 0.0 = straight down, 1.0 = the horizon
+@group(#{MATERIAL_BIND_GROUP}) @binding(5)
+var<storage, read> ground_stops: array<GradientStop>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(6)
+var<uniform> feature_ground_enabled: i32;
+@group(#{MATERIAL_BIND_GROUP}) @binding(7)
+var<uniform> ground: Ground;
 
 struct VertexOutput {
     @builtin(position) frag_pos: vec4<f32>,
     @location(0) world_dir: vec3<f32>,
+    // cycle percent 0.0 -> 1.0, only used by the lut
+    @location(1) @interpolate(flat) cycle_percent: f32,
 };
 
 @vertex
@@ -20,6 +36,7 @@ fn vertex(@location(0) position: vec3<f32>, @builtin(instance_index) vertin: u32
     var out: VertexOutput;
     let world_pos = position;
     out.world_dir = normalize(world_pos);
+    out.cycle_percent = bitcast<f32>(get_tag(vertin));
     out.frag_pos = mesh_position_local_to_clip(get_world_from_local(vertin), vec4<f32>(position, 1.0));
     return out;
 }
@@ -30,6 +47,53 @@ fn fragment(
     in: VertexOutput,
 ) -> @location(0) vec4<f32> {
     let view_dir = normalize(in.world_dir);
-    return gradient(view_dir, gradient_settings);
+    var color: vec4<f32>;
+    if feature_lut_enabled == 1 {
+        color = gradient_lut(in.cycle_percent, gradient_t(view_dir));
+    } else {
+        color = gradient(gradient_t(view_dir));
+    }
+    if feature_ground_enabled == 1 {
+        color = mix(ground_gradient(ground_t(view_dir, ground)), color, ground_blend(view_dir, ground));
+    }
+    return color;
 }
 
+// x = cycle percent, y = height, top row is straight up
+fn gradient_lut(cycle_percent: f32, t: f32) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(lut_texture));
+    // sample texel centers, the edges of the lut are exactly percent 0 and 1
+    let uv = (vec2<f32>(cycle_percent, 1.0 - t) * (size - 1.0) + 0.5) / size;
+    return textureSample(lut_texture, lut_texture_sampler, uv);
+}
+
+fn gradient(t: f32) -> vec4<f32> {
+    let num_stops = arrayLength(&gradient_stops);
+    // if below first stop
+    if (t <= gradient_stops[0].position) {
+        return gradient_stops[0].color;
+    }
+    // otherwise, find segment and interpolate
+    for (var i: u32 = 1u; i < num_stops; i = i + 1u) {
+        if (t <= gradient_stops[i].position) {
+            return mix_stops(gradient_stops[i - 1u], gradient_stops[i], t);
+        }
+    }
+    // above last stop
+    return gradient_stops[num_stops - 1u].color;
+}
+
+
+// same as gradient(), but for the colors below the horizon
+fn ground_gradient(t: f32) -> vec4<f32> {
+    let num_stops = arrayLength(&ground_stops);
+    if (t <= ground_stops[0].position) {
+        return ground_stops[0].color;
+    }
+    for (var i: u32 = 1u; i < num_stops; i = i + 1u) {
+        if (t <= ground_stops[i].position) {
+            return mix_stops(ground_stops[i - 1u], ground_stops[i], t);
+        }
+    }
+    return ground_stops[num_stops - 1u].color;
+}
@@ -3,10 +3,11 @@
     utils::coords_to_viewport_uv,
 }
 #import bevy_pbr::mesh_view_bindings::globals;
-#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip}
+#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip, get_tag}
 
 #import "bevy_sky_gradient/shaders/sun.wgsl"::{SunSettings, sun};
 #import "bevy_sky_gradient/shaders/stars.wgsl"::{StarsSettings, stars};
+#import "bevy_sky_gradient/shaders/gradient.wgsl"::{GradientStop, Ground, HorizonGlow, gradient_t, ground_t, ground_blend, mix_stops, horizon_glow, dither};
 
 @group(#{MATERIAL_BIND_GROUP}) @binding(0)
 var<uniform> sun_settings: SunSettings;
@@ -24,6 +25,8 @@ var<uniform> feature_stars_enabled: i32;
 var<uniform> feature_sun_enabled: i32;
 @group(#{MATERIAL_BIND_GROUP}) @binding(6)
 var<uniform> feature_aurora_enabled: i32;
+@group(#{MATERIAL_BIND_GROUP}) @binding(7)
+var<uniform> dither_strength: f32;
 
 @group(#{MATERIAL_BIND_GROUP}) @binding(10)
 var noise3_texture: texture_3d<f32>;
@@ -44,9 +47,31 @@ var gradient_texture: texture_2d<f32>;
 @group(#{MATERIAL_BIND_GROUP}) @binding(17)
 var gradient_texture_sampler: sampler;
 
+// the inline gradient, same as in full_gradient.wgsl
+@group(#{MATERIAL_BIND_GROUP}) @binding(18)
+var<uniform> feature_inline_gradient: i32;
+@group(#{MATERIAL_BIND_GROUP}) @binding(19)
+var<storage, read> gradient_stops: array<GradientStop>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(20)
+var<uniform> gradient_lut_enabled: i32;
+@group(#{MATERIAL_BIND_GROUP}) @binding(21)
+var lut_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(22)
+var lut_texture_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(23)
+var<uniform> glow: HorizonGlow;
+@group(#{MATERIAL_BIND_GROUP}) @binding(24)
+var<storage, read> ground_stops: array<GradientStop>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(25)
+var<uniform> feature_ground_enabled: i32;
+@group(#{MATERIAL_BIND_GROUP}) @binding(26)
+var<uniform> ground: Ground;
+
 struct VertexOutput {
     @builtin(position) frag_pos: vec4<f32>,
     @location(0) world_dir: vec3<f32>,
+    // cycle percent 0.0 -> 1.0, only used by the inline lut
+    @location(1) @interpolate(flat) cycle_percent: f32,
 };
 
 @vertex
@@ -54,6 +79,7 @@ fn vertex(@location(0) position: vec3<f32>, @builtin(instance_index) vertin: u32
     var out: VertexOutput;
     let world_pos = position; // since the sky sphere is centered on camera
     out.world_dir = normalize(world_pos);
+    out.cycle_percent = bitcast<f32>(get_tag(vertin));
     out.frag_pos = mesh_position_local_to_clip(get_world_from_local(vertin), vec4<f32>(position, 1.0));
     return out;
 }
@@ -72,9 +98,14 @@ fn fragment(
 
     var final_color = vec4f(0.0,0.0,0.0,1.0);
 
-    let screen_uv = in.frag_pos.xy / view.viewport.zw;
-    let gradient = textureSample(gradient_texture, gradient_texture_sampler, screen_uv).rgba;
-    final_color += gradient;
+    // the gradient and aurora textures cover the viewport, which may not start at the window corner
+    let screen_uv = coords_to_viewport_uv(in.frag_pos.xy, view.viewport);
+    if feature_inline_gradient == 1 {
+        final_color += inline_gradient(view_dir, in.cycle_percent);
+    } else {
+        final_color += textureSample(gradient_texture, gradient_texture_sampler, screen_uv).rgba;
+    }
+    final_color += horizon_glow(view_dir, glow);
 
     if feature_sun_enabled == 1 {
         // show sun in night but at less transparency
@@ -99,5 +130,52 @@ fn fragment(
         final_color += north * night_visibility;
     }
 
-    return final_color;
+    return dither(final_color, in.frag_pos.xy, dither_strength);
+}
+
+// what full_gradient.wgsl renders into the gradient texture
+fn inline_gradient(view_dir: vec3<f32>, cycle_percent: f32) -> vec4<f32> {
+    var color: vec4<f32>;
+    if gradient_lut_enabled == 1 {
+        color = gradient_lut(cycle_percent, gradient_t(view_dir));
+    } else {
+        color = gradient(gradient_t(view_dir));
+    }
+    if feature_ground_enabled == 1 {
+        color = mix(ground_gradient(ground_t(view_dir, ground)), color, ground_blend(view_dir, ground));
+    }
+    return color;
+}
+
+// x = cycle percent, y = height, top row is straight up
+fn gradient_lut(cycle_percent: f32, t: f32) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(lut_texture));
+    let uv = (vec2<f32>(cycle_percent, 1.0 - t) * (size - 1.0) + 0.5) / size;
+    return textureSample(lut_texture, lut_texture_sampler, uv);
+}
+
+fn gradient(t: f32) -> vec4<f32> {
+    let num_stops = arrayLength(&gradient_stops);
+    if (t <= gradient_stops[0].position) {
+        return gradient_stops[0].color;
+    }
+    for (var i: u32 = 1u; i < num_stops; i = i + 1u) {
+        if (t <= gradient_stops[i].position) {
+            return mix_stops(gradient_stops[i - 1u], gradient_stops[i], t);
+        }
+    }
+    return gradient_stops[num_stops - 1u].color;
+}
+
+fn ground_gradient(t: f32) -> vec4<f32> {
+    let num_stops = arrayLength(&ground_stops);
+    if (t <= ground_stops[0].position) {
+        return ground_stops[0].color;
+    }
+    for (var i: u32 = 1u; i < num_stops; i = i + 1u) {
+        if (t <= ground_stops[i].position) {
+            return mix_stops(ground_stops[i - 1u], ground_stops[i], t);
+        }
+    }
+    return ground_stops[num_stops - 1u].color;
 }
@@ -1,31 +1,73 @@
-struct GradientSettings {
-    color_stops: array<vec4<f32>, 4>,
-    positions: vec4<f32>,
-    num_stops: u32,
-}
-
-fn gradient(view_dir: vec3<f32>, gradient_settings: GradientSettings) -> vec4<f32> {
-    let t = clamp(view_dir.y * 0.5 + 0.5, 0.0, 1.0);
-    var base_color: vec4<f32> = gradient_settings.color_stops[0];
-    // if below first stop
-    if (t <= gradient_settings.positions[0]) {
-        base_color = gradient_settings.color_stops[0];
-    }
-    // if above last stop
-    else if (t >= gradient_settings.positions[gradient_settings.num_stops - 1u]) {
-        base_color = gradient_settings.color_stops[gradient_settings.num_stops - 1u];
-    }
-    // otherwise, find segment and interpolate
-    else {
-        for (var i: u32 = 1u; i < gradient_settings.num_stops; i = i + 1u) {
-            let a = gradient_settings.positions[i - 1u];
-            let b = gradient_settings.positions[i];
-            if (t >= a && t <= b) {
-                let f = (t - a) / (b - a);
-                base_color = mix(gradient_settings.color_stops[i - 1u], gradient_settings.color_stops[i], f);
-                break;
-            }
-        }
+// one sky color, matches GpuGradientStop in bind_groups.rs
+struct GradientStop {
+    color: vec4<f32>,
+    position: f32,
+}
+
+// where the ground meets the sky, matches GroundBindGroup in bind_groups.rs
+struct Ground {
+    horizon_offset: f32,
+    blend_width: f32,
+}
+
+// glow around the sun near the horizon, matches HorizonGlowBindGroup in bind_groups.rs
+struct HorizonGlow {
+    sun_dir: vec3<f32>,
+    color: vec4<f32>,
+    radius: f32,
+    falloff: f32,
+    height: f32,
+    sun_height_fade: f32,
+}
+
+// 0: looking straight down, 1: looking straight up
+fn gradient_t(view_dir: vec3<f32>) -> f32 {
+    return clamp(view_dir.y * 0.5 + 0.5, 0.0, 1.0);
+}
+
+// 0: looking straight down, 1: looking at the horizon
+fn ground_t(view_dir: vec3<f32>, ground: Ground) -> f32 {
+    return clamp((view_dir.y + 1.0) / max(ground.horizon_offset + 1.0, 0.0001), 0.0, 1.0);
+}
+
+// 0: only ground, 1: only sky
+fn ground_blend(view_dir: vec3<f32>, ground: Ground) -> f32 {
+    let half_width = max(ground.blend_width, 0.0001) * 0.5;
+    return smoothstep(ground.horizon_offset - half_width, ground.horizon_offset + half_width, view_dir.y);
+}
+
+// blend between two neighbouring stops, t is between their positions
+fn mix_stops(a: GradientStop, b: GradientStop, t: f32) -> vec4<f32> {
+    let f = (t - a.position) / max(b.position - a.position, 0.00001);
+    return mix(a.color, b.color, clamp(f, 0.0, 1.0));
+}
+
+// only adds color, the alpha of the gradient is kept
+fn horizon_glow(view_dir: vec3<f32>, glow: HorizonGlow) -> vec4<f32> {
+    // only the azimuth matters, compare the directions flattened onto the horizon
+    let view_flat = view_dir.xz / max(length(view_dir.xz), 0.0001);
+    let sun_flat = glow.sun_dir.xz / max(length(glow.sun_dir.xz), 0.0001);
+    let angle = acos(clamp(dot(view_flat, sun_flat), -1.0, 1.0));
+    let around_sun = pow(1.0 - clamp(angle / max(glow.radius, 0.0001), 0.0, 1.0), glow.falloff);
+    let near_horizon = 1.0 - smoothstep(0.0, max(glow.height, 0.0001), abs(view_dir.y));
+    let sun_near_horizon = 1.0 - smoothstep(0.0, max(glow.sun_height_fade, 0.0001), abs(normalize(glow.sun_dir).y));
+    let strength = around_sun * near_horizon * sun_near_horizon * glow.color.a;
+    return vec4<f32>(glow.color.rgb * strength, 0.0);
+}
+
+// 0.0 -> 1.0, a fixed pattern per pixel that looks like noise
+fn interleaved_gradient_noise(pixel: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+// breaks up banding. strength is in 8 bit color steps, 0.0 = off.
+// the noise is added in sRGB space, where the 8 bit steps are
+fn dither(color: vec4<f32>, pixel: vec2<f32>, strength: f32) -> vec4<f32> {
+    if strength <= 0.0 {
+        return color;
     }
-    return base_color;
+    // two samples give a triangle distribution -1.0 -> 1.0, evenly visible at every brightness
+    let noise = interleaved_gradient_noise(pixel) + interleaved_gradient_noise(pixel + vec2<f32>(73.0, 41.0)) - 1.0;
+    let srgb = pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(1.0 / 2.2)) + noise * strength / 255.0;
+    return vec4<f32>(pow(max(srgb, vec3<f32>(0.0)), vec3<f32>(2.2)), color.a);
 }
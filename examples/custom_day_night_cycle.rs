@@ -1,7 +1,12 @@
 use bevy::{color::palettes::css::WHITE, light::light_consts::lux::AMBIENT_DAYLIGHT, prelude::*};
 use bevy_flycam::{FlyCam, NoCameraPlayerPlugin};
 use bevy_sky_gradient::{
-    gradient::{GradientBuilder, SkyGradientBuilder},
+    ambient_driver::ScalarGradientBuilder,
+    bind_groups::HorizonGlowBindGroup,
+    gradient::{
+        GradientBuilder, GradientColor, GroundGradientBuilder, SkyGradientBuilder, SkyGradientStop,
+    },
+    keyframes::{KeyTime, KeyframeGradientBuilder},
     noise::NoiseHandles,
     plugin::{AuroraTextureHandle, GradientTextureHandle},
     prelude::*,
@@ -12,6 +17,17 @@ use bevy_sky_gradient::{
 // here we manually spawn: skybox, and our sun light
 // we also configure the cycle timings (long night)
 fn main() {
+    let mut sky_colors_builder = SkyGradientBuilder::new(CUSTOM_SKY_GRADIENT_STOPS);
+    // the top of the sky uses named keyframes instead of the fixed GradientBuilder slots
+    sky_colors_builder.stops[3].keyframes = Some(
+        KeyframeGradientBuilder::new()
+            .with_key("sunrise", KeyTime::Sunrise(0.0), [255, 0, 0, 255])
+            .with_key("noon", KeyTime::Day(0.5), [0, 195, 255, 255])
+            .with_key("golden hour", KeyTime::Sunset(-2.0), [255, 190, 60, 255])
+            .with_key("blue hour", KeyTime::Sunset(2.0), [40, 60, 170, 255])
+            .with_key("midnight", KeyTime::Night(0.5), [0, 0, 0, 245]),
+    );
+
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins(NoCameraPlayerPlugin)
@@ -27,6 +43,9 @@ fn main() {
                         sunset_time_sec: 0.2,
                     },
                     sky_time: SkyTime::default(),
+                    // linger around sunrise and sunset
+                    time_warp: SkyTimeWarp::PerHalf(EaseFunction::SmoothStep),
+                    ..default()
                 })
                 .set_sun_driver(SunDriverPlugin {
                     spawn_default_sun_light: false,
@@ -35,10 +54,20 @@ fn main() {
                         sun_color: vec4(1.0, 1.0, 0.0, 1.0),
                         sun_strength: default(),
                         sun_sharpness: default(),
+                        // deep red sunsets on the side of the sun
+                        horizon_glow: HorizonGlowBindGroup {
+                            color: vec4(1.0, 0.25, 0.1, 0.8),
+                            radius: 1.0,
+                            ..default()
+                        },
                     },
+                    ..default()
                 })
                 .set_gradient_driver(GradientDriverPlugin {
-                    sky_colors_builder: CUSTOM_SKY_COLORS_BUILDER,
+                    sky_colors_builder,
+                    // separate colors below the horizon
+                    ground_colors_builder: Some(GroundGradientBuilder::default()),
+                    ..default()
                 })
                 .build(),
         )
@@ -100,37 +129,65 @@ fn setup(
     ));
 }
 
-pub const CUSTOM_SKY_COLORS_BUILDER: SkyGradientBuilder = SkyGradientBuilder {
-    gradient_builder_stop0: GradientBuilder {
-        sunrise_color: [255, 0, 0, 255],
-        day_low_color: [0, 0, 248, 255],
-        day_high_color: [0, 48, 255, 255],
-        sunset_color: [255, 70, 70, 255],
-        night_low_color: [0, 0, 0, 245],
-        night_high_color: [0, 0, 0, 245],
+pub const CUSTOM_SKY_GRADIENT_STOPS: [SkyGradientStop; 4] = [
+    SkyGradientStop {
+        position: 0.38,
+        colors: GradientBuilder {
+            sunrise_color: GradientColor::Srgb([255, 0, 0, 255]),
+            day_low_color: GradientColor::Srgb([0, 0, 248, 255]),
+            day_high_color: GradientColor::Srgb([0, 48, 255, 255]),
+            sunset_color: GradientColor::Srgb([255, 70, 70, 255]),
+            night_low_color: GradientColor::Srgb([0, 0, 0, 245]),
+            night_high_color: GradientColor::Srgb([0, 0, 0, 245]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-    gradient_builder_stop1: GradientBuilder {
-        sunrise_color: [255, 0, 0, 255],
-        day_low_color: [0, 0, 255, 255],
-        day_high_color: [0, 226, 255, 255],
-        sunset_color: [243, 84, 47, 255],
-        night_low_color: [0, 0, 0, 245],
-        night_high_color: [0, 0, 0, 245],
+    SkyGradientStop {
+        position: 0.47,
+        colors: GradientBuilder {
+            sunrise_color: GradientColor::Srgb([255, 0, 0, 255]),
+            day_low_color: GradientColor::Srgb([0, 0, 255, 255]),
+            day_high_color: GradientColor::Srgb([0, 226, 255, 255]),
+            sunset_color: GradientColor::Srgb([243, 84, 47, 255]),
+            night_low_color: GradientColor::Srgb([0, 0, 0, 245]),
+            night_high_color: GradientColor::Srgb([0, 0, 0, 245]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-    gradient_builder_stop2: GradientBuilder {
-        sunrise_color: [255, 0, 0, 255],
-        day_low_color: [0, 0, 254, 255],
-        day_high_color: [0, 170, 255, 255],
-        sunset_color: [255, 242, 72, 255],
-        night_low_color: [0, 0, 0, 245],
-        night_high_color: [0, 0, 0, 245],
+    SkyGradientStop {
+        position: 0.61,
+        colors: GradientBuilder {
+            sunrise_color: GradientColor::Srgb([255, 0, 0, 255]),
+            day_low_color: GradientColor::Srgb([0, 0, 254, 255]),
+            day_high_color: GradientColor::Srgb([0, 170, 255, 255]),
+            sunset_color: GradientColor::Srgb([255, 242, 72, 255]),
+            night_low_color: GradientColor::Srgb([0, 0, 0, 245]),
+            night_high_color: GradientColor::Srgb([0, 0, 0, 245]),
+        },
+        keyframes: None,
+        // a thick sunset band, that thins out at noon
+        animated_position: Some(ScalarGradientBuilder {
+            sunrise_color: 0.72,
+            day_low_color: 0.61,
+            day_high_color: 0.53,
+            sunset_color: 0.75,
+            night_low_color: 0.61,
+            night_high_color: 0.61,
+        }),
     },
-    gradient_builder_stop3: GradientBuilder {
-        sunrise_color: [255, 0, 0, 255],
-        day_low_color: [0, 0, 255, 255],
-        day_high_color: [0, 195, 255, 255],
-        sunset_color: [73, 177, 250, 255],
-        night_low_color: [0, 0, 0, 245],
-        night_high_color: [0, 0, 0, 245],
+    SkyGradientStop {
+        position: 1.0,
+        colors: GradientBuilder {
+            sunrise_color: GradientColor::Srgb([255, 0, 0, 255]),
+            day_low_color: GradientColor::Srgb([0, 0, 255, 255]),
+            day_high_color: GradientColor::Srgb([0, 195, 255, 255]),
+            sunset_color: GradientColor::Srgb([73, 177, 250, 255]),
+            night_low_color: GradientColor::Srgb([0, 0, 0, 245]),
+            night_high_color: GradientColor::Srgb([0, 0, 0, 245]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-};
+];
@@ -1,6 +1,7 @@
 use crate::{
-    cycle::{SkyTime, SkyTimeSettings},
-    gradient::{Gradient, GradientBuilder, ScalarGradient},
+    cycle::{SkyTime, SkyTimeSettings, SkyTimeWarp},
+    gradient::{Gradient, GradientBuilder, GradientColor, ScalarGradient},
+    sun::SunPath,
 };
 use bevy::prelude::*;
 #[cfg(feature = "serde")]
@@ -20,6 +21,30 @@ pub struct ScalarGradientBuilder {
 }
 
 impl ScalarGradientBuilder {
+    /// the same value all day
+    pub fn constant(value: f32) -> Self {
+        Self {
+            sunrise_color: value,
+            day_low_color: value,
+            day_high_color: value,
+            sunset_color: value,
+            night_low_color: value,
+            night_high_color: value,
+        }
+    }
+
+    /// t: 0.0 = self, 1.0 = other
+    pub fn lerp(&self, other: &ScalarGradientBuilder, t: f32) -> ScalarGradientBuilder {
+        ScalarGradientBuilder {
+            sunrise_color: self.sunrise_color.lerp(other.sunrise_color, t),
+            day_low_color: self.day_low_color.lerp(other.day_low_color, t),
+            day_high_color: self.day_high_color.lerp(other.day_high_color, t),
+            sunset_color: self.sunset_color.lerp(other.sunset_color, t),
+            night_low_color: self.night_low_color.lerp(other.night_low_color, t),
+            night_high_color: self.night_high_color.lerp(other.night_high_color, t),
+        }
+    }
+
     pub fn build_gradient(&self, s: &SkyTimeSettings) -> ScalarGradient {
         let sunrise_end = s.sunrise_percent_day() * 0.5;
         let sunset_start = 0.5 - s.sunset_percent_day() * 0.5;
@@ -41,12 +66,29 @@ impl ScalarGradientBuilder {
 }
 
 /// the current ambient colors
-#[derive(Resource, Clone)]
+/// also a component, used by a camera with its own [`crate::instance::SkyInstance`]
+#[derive(Resource, Component, Clone)]
 pub struct AmbientColors {
     pub color_gradient: Gradient,
     pub brightness_gradient: ScalarGradient,
 }
 
+impl AmbientColors {
+    /// sample the gradients at the cycle `percent`, and write them into the ambient light
+    pub fn sample_into(
+        &self,
+        percent: f32,
+        ambient_settings: &AmbientSettings,
+        ambient_light: &mut AmbientLight,
+    ) {
+        let color = self.color_gradient.sample_at(percent);
+        let brightness = self.brightness_gradient.sample_at(percent);
+
+        ambient_light.color = Color::srgb(color[0], color[1], color[2]);
+        ambient_light.brightness = brightness * ambient_settings.brightness_multiplier;
+    }
+}
+
 impl Default for AmbientColors {
     fn default() -> Self {
         Self {
@@ -72,7 +114,8 @@ impl Default for AmbientSettings {
     }
 }
 
-#[derive(Resource, Clone, Reflect)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Asset, Clone, Reflect)]
 pub struct AmbientColorsBuilder {
     pub color_gradient: GradientBuilder,
     pub scalar_gradient: ScalarGradientBuilder,
@@ -82,12 +125,12 @@ impl Default for AmbientColorsBuilder {
     fn default() -> Self {
         Self {
             color_gradient: GradientBuilder {
-                sunrise_color: [255, 255, 200, 255],
-                day_low_color: [255, 255, 150, 255],
-                day_high_color: [255, 255, 200, 255],
-                sunset_color: [240, 240, 255, 255],
-                night_low_color: [150, 150, 225, 255],
-                night_high_color: [100, 100, 150, 255],
+                sunrise_color: GradientColor::Srgb([255, 255, 200, 255]),
+                day_low_color: GradientColor::Srgb([255, 255, 150, 255]),
+                day_high_color: GradientColor::Srgb([255, 255, 200, 255]),
+                sunset_color: GradientColor::Srgb([240, 240, 255, 255]),
+                night_low_color: GradientColor::Srgb([150, 150, 225, 255]),
+                night_high_color: GradientColor::Srgb([100, 100, 150, 255]),
             },
             scalar_gradient: ScalarGradientBuilder {
                 sunrise_color: 0.4,
@@ -151,7 +194,7 @@ impl Plugin for AmbientDriverPlugin {
 }
 
 /// rebuild the ambient gradients because SkyTimeSettings changed
-fn update_ambient_colors_builder(
+pub(crate) fn update_ambient_colors_builder(
     sky_time_settings: Res<SkyTimeSettings>,
     mut ambient_colors: ResMut<AmbientColors>,
     ambient_colors_builder: Res<AmbientColorsBuilder>,
@@ -162,15 +205,16 @@ fn update_ambient_colors_builder(
 fn drive_ambience(
     sky_time_settings: Res<SkyTimeSettings>,
     sky_time: Res<SkyTime>,
+    sky_time_warp: Res<SkyTimeWarp>,
+    sun_path: Option<Res<SunPath>>,
     ambient_settings: Res<AmbientSettings>,
     ambient_colors: Res<AmbientColors>,
     mut ambient_light: ResMut<AmbientLight>,
 ) {
-    let percent = sky_time_settings.time_percent(sky_time.time);
-
-    let color = ambient_colors.color_gradient.sample_at(percent);
-    let brightness = ambient_colors.brightness_gradient.sample_at(percent);
-
-    ambient_light.color = Color::srgb(color[0], color[1], color[2]);
-    ambient_light.brightness = brightness * ambient_settings.brightness_multiplier;
+    let time = sky_time_warp.warp_time(&sky_time_settings, sky_time.time);
+    let time = sun_path.map_or(time, |sun_path| {
+        sun_path.gradient_time(&sky_time_settings, time)
+    });
+    let percent = sky_time_settings.time_percent(time);
+    ambient_colors.sample_into(percent, &ambient_settings, &mut ambient_light);
 }
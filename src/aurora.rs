@@ -1,15 +1,10 @@
-use bevy::{
-    camera::visibility::RenderLayers,
-    prelude::*,
-    render::render_resource::Extent3d,
-    transform::plugins::TransformSystems,
-    window::{PrimaryWindow, WindowResized},
-};
+use bevy::{camera::visibility::RenderLayers, prelude::*, transform::plugins::TransformSystems};
 
 use crate::{
     aurora_material::AuroraMaterial,
+    magnet_rig::MagnetRigOf,
     noise::{NoiseHandles, setup_noise_texture},
-    plugin::{AuroraTextureHandle, SkyboxMagnetTag, spawn_aurora_texture},
+    plugin::{AuroraTextureHandle, PrimaryMagnetFilter, SkySettings, spawn_aurora_texture},
     utils,
 };
 
@@ -19,7 +14,7 @@ pub struct AuroraCameraTag;
 #[derive(Resource, Reflect, Clone)]
 pub struct AuroraSettings {
     /// controlls size of the render target of the aurora material
-    /// a value of 1.0: use 100% of the windows screen size. aka full quality.
+    /// a value of 1.0: use 100% of the camera's viewport size. aka full quality.
     /// a value of 0.5: will render the aurora 50% of the screen and be upscaled 200%
     pub render_texture_percent: f32,
     /// what render layer the aurora will render on
@@ -61,8 +56,7 @@ impl Plugin for AuroraPlugin {
         );
         app.add_systems(
             PostUpdate,
-            (aurora_follow_camera, resize_aurora_on_window_change)
-                .before(TransformSystems::Propagate),
+            (aurora_follow_camera, resize_aurora_to_camera).before(TransformSystems::Propagate),
         );
     }
 }
@@ -70,12 +64,18 @@ impl Plugin for AuroraPlugin {
 fn aurora_follow_camera(
     primary_cameras: Query<
         (&Transform, &Camera, &Projection),
-        (Without<AuroraCameraTag>, With<SkyboxMagnetTag>),
+        (Without<AuroraCameraTag>, PrimaryMagnetFilter),
     >,
     mut aurora_cameras: Query<(&mut Transform, &Camera, &mut Projection), With<AuroraCameraTag>>,
-    mut aurora_mesh: Query<&mut Transform, (Without<Camera>, With<MeshMaterial3d<AuroraMaterial>>)>,
+    mut aurora_mesh: Query<
+        &mut Transform,
+        (
+            Without<Camera>,
+            With<MeshMaterial3d<AuroraMaterial>>,
+            Without<MagnetRigOf>,
+        ),
+    >,
 ) {
-    // find active camera TODO: IDENTIFY THE CORRECT CAMERA BETTER
     if let Some((cam_tf, _camera, cam_proj)) = primary_cameras.iter().next() {
         for (mut aurora_tf, _cam, mut aurora_projection) in aurora_cameras.iter_mut() {
             // ensure same projection
@@ -88,51 +88,23 @@ fn aurora_follow_camera(
     }
 }
 
-fn resize_aurora_on_window_change(
-    mut resize_events: MessageReader<WindowResized>,
+// a percent of what the primary SkyboxMagnetTag camera renders: its viewport, or its whole window or image
+fn resize_aurora_to_camera(
     mut images: ResMut<Assets<Image>>,
     aurora_handles: Res<AuroraTextureHandle>,
     aurora_settings: Res<AuroraSettings>,
-    primary_windows: Query<&Window, With<PrimaryWindow>>,
-    mut repeated_calls: Local<i32>,
+    magnet_cameras: Query<&Camera, PrimaryMagnetFilter>,
 ) {
-    let mut update_aurora = aurora_settings.is_changed();
-
-    for event in resize_events.read() {
-        let is_primary = primary_windows.get(event.window).is_ok();
-        update_aurora |= is_primary;
-    }
-    if !update_aurora {
-        *repeated_calls = 0;
-        return;
-    }
-
-    *repeated_calls += 1;
-    if *repeated_calls > 10 {
-        warn!(
-            "aurora texture, was resized every last:{} frames!",
-            *repeated_calls
-        );
-        warn!("make sure AuroraSettings doesn't mutate every frame");
-    }
-
-    let Ok(window) = primary_windows.single() else {
+    let Some(size) = magnet_cameras
+        .iter()
+        .next()
+        .and_then(Camera::physical_viewport_size)
+    else {
         return;
     };
-    let aspect = window.width() / window.height();
-
-    let width = (window.width() * aurora_settings.render_texture_percent.clamp(0.0, 1.0)) as u32;
-    let height = (width as f32 / aspect.max(0.0001)) as u32;
-    let width = width.max(2);
-    let height = height.max(2);
-
-    if let Some(image) = images.get_mut(&aurora_handles.render_target) {
-        image.resize(Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        });
-    }
+    let percent = aurora_settings.render_texture_percent.clamp(0.0, 1.0);
+    let size = (size.as_vec2() * percent).as_uvec2();
+    utils::resize_render_target(&mut images, &aurora_handles.render_target, size);
 }
 
 fn spawn_aurora_skybox(
@@ -142,6 +114,7 @@ fn spawn_aurora_skybox(
     noise_handles: Res<NoiseHandles>,
     aurora_texture_handle: Res<AuroraTextureHandle>,
     aurora_settings: Res<AuroraSettings>,
+    sky_settings: Res<SkySettings>,
 ) {
     commands.spawn((
         Name::new("sky_aurora_skybox"),
@@ -155,17 +128,19 @@ fn spawn_aurora_skybox(
     ));
 
     // AURORA CAMERA
-    commands.spawn((
-        Name::new("camera_aurora"),
-        Camera3d::default(),
-        AuroraCameraTag,
-        Camera {
-            order: aurora_settings.camera_order,
-            target: aurora_texture_handle.render_target.clone().into(),
-            clear_color: ClearColorConfig::Custom(Color::NONE),
-            ..default()
-        },
-        Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)).looking_at(Vec3::ZERO, Vec3::Y),
-        aurora_settings.camera_render_layers.clone(),
-    ));
+    commands
+        .spawn((
+            Name::new("camera_aurora"),
+            Camera3d::default(),
+            AuroraCameraTag,
+            Camera {
+                order: aurora_settings.camera_order,
+                target: aurora_texture_handle.render_target.clone().into(),
+                clear_color: ClearColorConfig::Custom(Color::NONE),
+                ..default()
+            },
+            Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)).looking_at(Vec3::ZERO, Vec3::Y),
+            aurora_settings.camera_render_layers.clone(),
+        ))
+        .insert_if(utils::hdr_render_target_camera(), || sky_settings.hdr);
 }
@@ -3,31 +3,95 @@ use bevy::{prelude::*, render::render_resource::ShaderType};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// the sky colors, written into a storage buffer by [`crate::gradient_material::FullGradientMaterial`]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug, Reflect, ShaderType)]
+#[derive(Clone, Debug, PartialEq, Reflect)]
 pub struct GradientBindGroup {
-    ///! the colors of sky
-    pub color_stops: [Vec4; 4],
-    ///! where the color gradients are positioned
-    pub positions: Vec4,
-    ///! how many sky colors to make gradient of (max 4)
-    pub num_stops: u32,
+    ///! the colors of sky, from the bottom to the top
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_list"))]
+    pub color_stops: Vec<Vec4>,
+    ///! where the color gradients are positioned, one per color stop, sorted 0.0 -> 1.0
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_list"))]
+    pub positions: Vec<f32>,
 }
 
 impl Default for GradientBindGroup {
     fn default() -> Self {
-        let color_stops = [
-            Vec4::new(0.2, 0.3, 0.6, 1.0),
-            Vec4::new(0.4, 0.5, 1.0, 1.0),
-            Vec4::new(0.35, 0.6, 0.8, 1.0),
-            Vec4::new(0.5, 0.7, 1.0, 1.0),
-        ];
         GradientBindGroup {
-            color_stops,
-            positions: Vec4::new(0.38, 0.47, 0.61, 1.0),
-            num_stops: 4,
+            color_stops: vec![
+                Vec4::new(0.2, 0.3, 0.6, 1.0),
+                Vec4::new(0.4, 0.5, 1.0, 1.0),
+                Vec4::new(0.35, 0.6, 0.8, 1.0),
+                Vec4::new(0.5, 0.7, 1.0, 1.0),
+            ],
+            positions: vec![0.38, 0.47, 0.61, 1.0],
+        }
+    }
+}
+
+impl GradientBindGroup {
+    /// the stops as laid out in the storage buffer.
+    /// never empty, an empty gradient becomes a single transparent stop
+    pub fn gpu_stops(&self) -> Vec<GpuGradientStop> {
+        let stops: Vec<GpuGradientStop> = self
+            .color_stops
+            .iter()
+            .zip(&self.positions)
+            .map(|(color, position)| GpuGradientStop {
+                color: *color,
+                position: *position,
+            })
+            .collect();
+        if stops.is_empty() {
+            return vec![GpuGradientStop {
+                color: Vec4::ZERO,
+                position: 0.0,
+            }];
+        }
+        stops
+    }
+}
+
+/// a single sky color, matches `GradientStop` in gradient.wgsl
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct GpuGradientStop {
+    pub color: Vec4,
+    pub position: f32,
+}
+
+// presets saved when the sky had exactly 4 colors store fixed size tuples: (a, b, c, d)
+// read both those and lists
+#[cfg(feature = "serde")]
+fn deserialize_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct ListVisitor<T>(std::marker::PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> serde::de::Visitor<'de> for ListVisitor<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a list or a tuple")
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+            let mut values = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
         }
     }
+
+    let visitor = ListVisitor(std::marker::PhantomData);
+    // compact formats like bincode can't deserialize_any, they always store lists
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(visitor)
+    } else {
+        deserializer.deserialize_seq(visitor)
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -78,6 +142,72 @@ impl Default for SunBindGroup {
     }
 }
 
+/// where the ground colors meet the sky. matches `Ground` in gradient.wgsl
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Reflect, ShaderType)]
+pub struct GroundBindGroup {
+    /// view height of the horizon, -1.0 (straight down) -> 1.0 (straight up).
+    /// below 0.0 when high up, like on a mountain top or in a plane
+    pub horizon_offset: f32,
+    /// how far around the horizon the ground fades into the sky, in view height
+    pub blend_width: f32,
+}
+
+impl GroundBindGroup {
+    /// 0.0 looking straight down, 1.0 looking at the horizon. matches `ground_t` in gradient.wgsl
+    pub fn ground_t(&self, view_y: f32) -> f32 {
+        ((view_y + 1.0) / (self.horizon_offset + 1.0).max(0.0001)).clamp(0.0, 1.0)
+    }
+
+    /// 0.0 only ground, 1.0 only sky. matches `ground_blend` in gradient.wgsl
+    pub fn ground_blend(&self, view_y: f32) -> f32 {
+        let half_width = self.blend_width.max(0.0001) * 0.5;
+        let t = ((view_y - self.horizon_offset + half_width) / (half_width * 2.0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Default for GroundBindGroup {
+    fn default() -> Self {
+        Self {
+            horizon_offset: 0.0,
+            blend_width: 0.05,
+        }
+    }
+}
+
+/// warm glow around the sun, near the horizon. matches `HorizonGlow` in gradient.wgsl
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Reflect, ShaderType)]
+pub struct HorizonGlowBindGroup {
+    /// auto set by the sun driver, direction pointing at the sun
+    pub sun_dir: Vec3,
+    /// added on top of the gradient, alpha scales the strength. can go above 1.0
+    pub color: Vec4,
+    /// radians, how far from the sun (around the horizon) the glow reaches
+    pub radius: f32,
+    /// higher = the glow fades out quicker away from the sun
+    pub falloff: f32,
+    /// 0.0 -> 1.0, how far above and below the horizon the glow reaches
+    pub height: f32,
+    /// the glow is strongest with the sun at the horizon,
+    /// and gone once the sun is this high above or below it. 0.0 -> 1.0 (sun_dir.y)
+    pub sun_height_fade: f32,
+}
+
+impl Default for HorizonGlowBindGroup {
+    fn default() -> Self {
+        Self {
+            sun_dir: Vec3::new(0.0, 0.1, -1.0),
+            color: Vec4::new(1.0, 0.45, 0.2, 0.6),
+            radius: 1.4,
+            falloff: 2.0,
+            height: 0.35,
+            sun_height_fade: 0.35,
+        }
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, Reflect, ShaderType)]
 pub struct AuroraBindGroup {
@@ -1,6 +1,14 @@
 use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::sky_material::FullSkyMaterial;
+use crate::{
+    gradient::ScalarGradient,
+    plugin::DefaultSkyFilter,
+    sky_material::FullSkyMaterial,
+    sun::SunPath,
+    time_source::{SkyTimeAuthority, SkyTimeSource, apply_sky_time_authority},
+};
 
 /// introduce a sky timer that our SunDriver+GradientDriver
 /// can use to animate the sky over time
@@ -8,6 +16,10 @@ use crate::sky_material::FullSkyMaterial;
 pub struct SkyCyclePlugin {
     pub sky_time_settings: SkyTimeSettings,
     pub sky_time: SkyTime,
+    /// what advances the sky time, local frame time by default
+    pub time_source: SkyTimeSource,
+    /// how the sky time maps onto the visible sky, linear by default
+    pub time_warp: SkyTimeWarp,
 }
 
 impl Default for SkyCyclePlugin {
@@ -15,6 +27,8 @@ impl Default for SkyCyclePlugin {
         Self {
             sky_time_settings: SkyTimeSettings::default(),
             sky_time: SkyTime::default(),
+            time_source: SkyTimeSource::default(),
+            time_warp: SkyTimeWarp::default(),
         }
     }
 }
@@ -23,29 +37,104 @@ impl Plugin for SkyCyclePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(self.sky_time.clone());
         app.insert_resource(self.sky_time_settings.clone());
-        app.add_systems(Update, (update_sky_time, drive_night_time).chain());
+        app.insert_resource(self.time_source.clone());
+        app.register_type::<SkyTimeWarp>();
+        app.insert_resource(self.time_warp.clone());
+        app.init_resource::<SkyTimeAuthority>();
+        app.add_message::<SkyPhaseChanged>();
+        // validate in PreUpdate, so everything in Update sees valid settings
+        app.add_systems(
+            PreUpdate,
+            validate_sky_time_settings.run_if(resource_changed::<SkyTimeSettings>),
+        );
+        app.add_systems(
+            FixedUpdate,
+            update_sky_time.run_if(resource_equals(SkyTimeSource::Fixed)),
+        );
+        app.add_systems(
+            Update,
+            (
+                update_sky_time.run_if(not(resource_equals(SkyTimeSource::Fixed))),
+                apply_sky_time_authority.run_if(resource_equals(SkyTimeSource::Authoritative)),
+                emit_sky_phase_changed,
+                drive_night_time,
+            )
+                .chain(),
+        );
     }
 }
 
-fn update_sky_time(
+// invalid settings, such as from the inspector, are clamped instead of breaking the gradients
+fn validate_sky_time_settings(mut sky_time_settings: ResMut<SkyTimeSettings>) {
+    if let Err(err) = sky_time_settings.validate() {
+        let clamped = sky_time_settings.clamped();
+        warn!("SkyPlugin: invalid SkyTimeSettings, clamping. reason: {err}");
+        *sky_time_settings = clamped;
+    }
+}
+
+pub(crate) fn update_sky_time(
     mut sky_time: ResMut<SkyTime>,
     time: Res<Time>,
     sky_time_settings: Res<SkyTimeSettings>,
 ) {
-    if !sky_time.auto_tick {
-        return;
-    }
-    sky_time.time += time.delta_secs();
-    if sky_time.time > sky_time_settings.total_time() {
-        sky_time.time -= sky_time_settings.total_time();
+    sky_time.tick(time.delta_secs(), &sky_time_settings);
+}
+
+/// sent when the sky time crosses from one DayPhase into another.
+/// a jump backwards, or of a day or more, sends one change straight to the new phase,
+/// see [`SkyTimeSettings::phase_changes`]
+#[derive(Message, Clone, Copy, Debug, PartialEq)]
+pub struct SkyPhaseChanged {
+    pub from: DayPhase,
+    pub to: DayPhase,
+    /// the SkyTime.day the change happened
+    pub day: u32,
+}
+
+/// the phases of a day, timed the same way the sky gradients are built.
+/// Sunrise: sunrise_time_sec before and after the day starts
+/// Sunset: sunset_time_sec before and after the night starts
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DayPhase {
+    Sunrise,
+    Day,
+    Sunset,
+    Night,
+}
+
+impl DayPhase {
+    pub fn next(&self) -> DayPhase {
+        match self {
+            DayPhase::Sunrise => DayPhase::Day,
+            DayPhase::Day => DayPhase::Sunset,
+            DayPhase::Sunset => DayPhase::Night,
+            DayPhase::Night => DayPhase::Sunrise,
+        }
     }
 }
 
+// messages for every phase boundary passed since last frame
+fn emit_sky_phase_changed(
+    sky_time: Res<SkyTime>,
+    sky_time_settings: Res<SkyTimeSettings>,
+    mut phase_changed: MessageWriter<SkyPhaseChanged>,
+    mut previous: Local<Option<(u32, f32)>>,
+) {
+    let current = (sky_time.day, sky_time.time);
+    let Some(previous) = previous.replace(current) else {
+        return;
+    };
+    phase_changed.write_batch(sky_time_settings.phase_changes(previous, current));
+}
+
 // inform sky material the time!
 fn drive_night_time(
     sky_time_settings: Res<SkyTimeSettings>,
     sky_time: Res<SkyTime>,
-    skyboxes: Query<&mut MeshMaterial3d<FullSkyMaterial>>,
+    sky_time_warp: Res<SkyTimeWarp>,
+    sun_path: Option<Res<SunPath>>,
+    skyboxes: Query<&mut MeshMaterial3d<FullSkyMaterial>, DefaultSkyFilter>,
     mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
 ) {
     let skybox_material_handle = skyboxes
@@ -54,14 +143,27 @@ fn drive_night_time(
     let skybox_material = sky_materials
         .get_mut(skybox_material_handle)
         .expect("SkyBoxMaterial");
-    skybox_material.night_time_distance = sky_time_settings.night_time_distance(sky_time.time);
+    let time = sky_time_warp.warp_time(&sky_time_settings, sky_time.time);
+    let time = sun_path.map_or(time, |sun_path| {
+        sun_path.gradient_time(&sky_time_settings, time)
+    });
+    skybox_material.night_time_distance = sky_time_settings.night_time_distance(time);
 }
 
 /// The current sky time
-#[derive(Resource, Reflect, Clone)]
+/// also a component, used by a camera with its own [`crate::instance::SkyInstance`]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Component, Reflect, Clone)]
 pub struct SkyTime {
     pub time: f32,
+    /// false = paused
     pub auto_tick: bool,
+    /// how many full cycles has passed, increased every time `time` wraps around
+    pub day: u32,
+    /// time multiplier, 2.0 makes the sky cycle twice as fast
+    pub speed: f32,
+    /// an active smooth transition, see [`SkyTime::transition_to`]
+    pub transition: Option<SkyTimeTransition>,
 }
 
 impl Default for SkyTime {
@@ -69,12 +171,185 @@ impl Default for SkyTime {
         Self {
             time: 0.0,
             auto_tick: true,
+            day: 0,
+            speed: 1.0,
+            transition: None,
         }
     }
 }
 
+impl SkyTime {
+    /// current wall-clock time, see [`SkyTimeSettings::hours`]
+    pub fn time_of_day(&self, sky_time_settings: &SkyTimeSettings) -> TimeOfDay {
+        TimeOfDay::from_hours(sky_time_settings.hours(self.time))
+    }
+
+    /// jump to a wall-clock time within the current day. example: `set_time_of_day(&settings, 18, 30)`
+    pub fn set_time_of_day(&mut self, sky_time_settings: &SkyTimeSettings, hour: u32, minute: u32) {
+        self.time = sky_time_settings.time_from_hours(TimeOfDay::new(hour, minute).hours());
+    }
+
+    /// smoothly move forward to `target_time` over `duration_sec` seconds.
+    /// wraps into the next day if `target_time` is earlier than the current time
+    pub fn transition_to(
+        &mut self,
+        sky_time_settings: &SkyTimeSettings,
+        target_time: f32,
+        duration_sec: f32,
+    ) {
+        let distance = (target_time - self.time).rem_euclid(sky_time_settings.total_time());
+        self.transition = Some(SkyTimeTransition::new(distance, duration_sec));
+    }
+
+    /// smoothly move forward to a wall-clock time. example: sleep until 06:30
+    pub fn transition_to_time_of_day(
+        &mut self,
+        sky_time_settings: &SkyTimeSettings,
+        hour: u32,
+        minute: u32,
+        duration_sec: f32,
+    ) {
+        let target_time = sky_time_settings.time_from_hours(TimeOfDay::new(hour, minute).hours());
+        self.transition_to(sky_time_settings, target_time, duration_sec);
+    }
+
+    /// advance the time by `delta_secs`, respecting pause, speed and transitions
+    pub fn tick(&mut self, delta_secs: f32, sky_time_settings: &SkyTimeSettings) {
+        if !self.auto_tick {
+            return;
+        }
+        if let Some(transition) = &mut self.transition {
+            // while transitioning, the transition owns the time
+            let step = transition.step(delta_secs);
+            if transition.is_finished() {
+                self.transition = None;
+            }
+            self.time += step;
+        } else {
+            self.time += delta_secs * self.speed;
+        }
+        self.wrap(sky_time_settings);
+    }
+
+    /// keep `time` within 0 -> total_time, counting days as it wraps
+    pub fn wrap(&mut self, sky_time_settings: &SkyTimeSettings) {
+        let total_time = sky_time_settings.total_time();
+        if total_time <= 0.0 {
+            return;
+        }
+        // loop, a large delta time can skip multiple days
+        while self.time > total_time {
+            self.time -= total_time;
+            self.day += 1;
+        }
+        while self.time < 0.0 {
+            self.time += total_time;
+            self.day = self.day.saturating_sub(1);
+        }
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        self.auto_tick = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.auto_tick = true;
+    }
+}
+
+/// moves SkyTime forward a fixed distance, eased in and out over a duration
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Reflect, Clone, Debug)]
+pub struct SkyTimeTransition {
+    /// how much sky time to move forward in total
+    pub distance: f32,
+    /// how many real seconds the transition takes
+    pub duration: f32,
+    pub elapsed: f32,
+    /// how much of the distance has been applied so far
+    pub progressed: f32,
+}
+
+impl SkyTimeTransition {
+    pub fn new(distance: f32, duration: f32) -> Self {
+        Self {
+            distance,
+            duration,
+            elapsed: 0.0,
+            progressed: 0.0,
+        }
+    }
+
+    /// advance the transition, returns how much sky time to add this step
+    pub fn step(&mut self, delta_secs: f32) -> f32 {
+        self.elapsed += delta_secs;
+        let t = if self.duration > 0.0 {
+            (self.elapsed / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        // smoothstep, so the sky eases in and out of the transition
+        let eased = t * t * (3.0 - 2.0 * t);
+        let target = self.distance * eased;
+        let step = target - self.progressed;
+        self.progressed = target;
+        step
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// the hour of the clock where the sun rises, when `SkyTime.time` is 0
+pub const SUNRISE_HOUR: f32 = 6.0;
+/// the hour of the clock where the sun sets, when `SkyTime.time` is `day_time_sec`
+pub const SUNSET_HOUR: f32 = 18.0;
+
+/// wall-clock representation of the sky time, "18:30"
+/// the day half of the cycle is mapped onto 06:00 -> 18:00
+/// the night half of the cycle is mapped onto 18:00 -> 06:00
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct TimeOfDay {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl TimeOfDay {
+    /// hour wraps at 24, minute wraps at 60
+    pub fn new(hour: u32, minute: u32) -> Self {
+        Self {
+            hour: (hour + minute / 60) % 24,
+            minute: minute % 60,
+        }
+    }
+
+    /// fractional hours 0.0 -> 24.0, 18.5 becomes 18:30
+    pub fn from_hours(hours: f32) -> Self {
+        let total_minutes = (hours.rem_euclid(24.0) * 60.0) as u32;
+        Self::new(total_minutes / 60, total_minutes % 60)
+    }
+
+    /// fractional hours 0.0 -> 24.0, 18:30 becomes 18.5
+    pub fn hours(&self) -> f32 {
+        self.hour as f32 + self.minute as f32 / 60.0
+    }
+}
+
+impl std::fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
 /// the sky timings
-#[derive(Resource, Clone, Reflect)]
+/// also a component, used by a camera with its own [`crate::instance::SkyInstance`]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Component, Clone, Reflect, PartialEq)]
 pub struct SkyTimeSettings {
     /// how many seconds of day light
     pub day_time_sec: f32,
@@ -97,7 +372,170 @@ impl Default for SkyTimeSettings {
     }
 }
 
+/// remaps the sky time before it reaches the gradient, sun and ambient drivers,
+/// lets parts of the day linger while others pass quickly.
+/// SkyTime itself, day phases and time of day are not warped.
+/// also a component, used by a camera with its own [`crate::instance::SkyInstance`]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Component, Reflect, Clone, Default)]
+pub enum SkyTimeWarp {
+    /// the sky follows the sky time as is
+    #[default]
+    Linear,
+    /// ease the day and the night individually.
+    /// `EaseFunction::SmoothStep` lingers around sunrise/sunset and passes noon/midnight quickly
+    PerHalf(EaseFunction),
+    /// remap the cycle percent 0.0 -> 1.0 (0.5 = sunset) through a curve.
+    /// should start at (0, 0), end at (1, 1) and never decrease
+    Curve(ScalarGradient),
+}
+
+impl SkyTimeWarp {
+    /// warp a cycle percent 0.0 -> 1.0, see [`SkyTimeSettings::time_percent`]
+    pub fn warp_percent(&self, percent: f32) -> f32 {
+        match self {
+            SkyTimeWarp::Linear => percent,
+            SkyTimeWarp::PerHalf(ease) => {
+                if percent <= 0.5 {
+                    ease.sample_clamped(percent * 2.0) * 0.5
+                } else {
+                    0.5 + ease.sample_clamped((percent - 0.5) * 2.0) * 0.5
+                }
+            }
+            SkyTimeWarp::Curve(curve) => curve.sample_at(percent).clamp(0.0, 1.0),
+        }
+    }
+
+    /// the sky time the sky should be rendered at
+    pub fn warp_time(&self, sky_time_settings: &SkyTimeSettings, time: f32) -> f32 {
+        if matches!(self, SkyTimeWarp::Linear) {
+            return time;
+        }
+        let percent = self.warp_percent(sky_time_settings.time_percent(time));
+        sky_time_settings.time_from_percent(percent)
+    }
+}
+
+/// why a SkyTimeSettings would produce a broken sky
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SkyTimeSettingsError {
+    #[error("{field} must be a finite number, got {value}")]
+    NotFinite { field: &'static str, value: f32 },
+    #[error("day_time_sec must be greater than 0, got {0}")]
+    DayTimeNotPositive(f32),
+    #[error("night_time_sec must be greater than 0, got {0}")]
+    NightTimeNotPositive(f32),
+    #[error("sunrise_time_sec must not be negative, got {0}")]
+    NegativeSunrise(f32),
+    #[error("sunset_time_sec must not be negative, got {0}")]
+    NegativeSunset(f32),
+    #[error(
+        "sunrise_time_sec + sunset_time_sec ({transitions}) exceeds day_time_sec ({day_time_sec})"
+    )]
+    TransitionsExceedDay { transitions: f32, day_time_sec: f32 },
+    #[error(
+        "sunrise_time_sec + sunset_time_sec ({transitions}) exceeds night_time_sec ({night_time_sec})"
+    )]
+    TransitionsExceedNight {
+        transitions: f32,
+        night_time_sec: f32,
+    },
+}
+
+/// smallest allowed day_time_sec and night_time_sec when clamping
+const MIN_CYCLE_HALF_SEC: f32 = 0.001;
+
 impl SkyTimeSettings {
+    /// validated constructor, see [`SkyTimeSettings::validate`]
+    pub fn new(
+        day_time_sec: f32,
+        night_time_sec: f32,
+        sunrise_time_sec: f32,
+        sunset_time_sec: f32,
+    ) -> Result<Self, SkyTimeSettingsError> {
+        let settings = Self {
+            day_time_sec,
+            night_time_sec,
+            sunrise_time_sec,
+            sunset_time_sec,
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// the sunrise and sunset are placed on both sides of the day/night border,
+    /// so together they have to fit within both the day and the night
+    pub fn validate(&self) -> Result<(), SkyTimeSettingsError> {
+        for (field, value) in [
+            ("day_time_sec", self.day_time_sec),
+            ("night_time_sec", self.night_time_sec),
+            ("sunrise_time_sec", self.sunrise_time_sec),
+            ("sunset_time_sec", self.sunset_time_sec),
+        ] {
+            if !value.is_finite() {
+                return Err(SkyTimeSettingsError::NotFinite { field, value });
+            }
+        }
+        if self.day_time_sec <= 0.0 {
+            return Err(SkyTimeSettingsError::DayTimeNotPositive(self.day_time_sec));
+        }
+        if self.night_time_sec <= 0.0 {
+            return Err(SkyTimeSettingsError::NightTimeNotPositive(
+                self.night_time_sec,
+            ));
+        }
+        if self.sunrise_time_sec < 0.0 {
+            return Err(SkyTimeSettingsError::NegativeSunrise(self.sunrise_time_sec));
+        }
+        if self.sunset_time_sec < 0.0 {
+            return Err(SkyTimeSettingsError::NegativeSunset(self.sunset_time_sec));
+        }
+        let transitions = self.sunrise_time_sec + self.sunset_time_sec;
+        if transitions > self.day_time_sec {
+            return Err(SkyTimeSettingsError::TransitionsExceedDay {
+                transitions,
+                day_time_sec: self.day_time_sec,
+            });
+        }
+        if transitions > self.night_time_sec {
+            return Err(SkyTimeSettingsError::TransitionsExceedNight {
+                transitions,
+                night_time_sec: self.night_time_sec,
+            });
+        }
+        Ok(())
+    }
+
+    /// closest valid settings. non finite values are reset to default,
+    /// and sunrise + sunset are shrunk to fit within the day and night
+    pub fn clamped(&self) -> Self {
+        let default = Self::default();
+        let finite_or = |value: f32, fallback: f32| {
+            if value.is_finite() { value } else { fallback }
+        };
+        let day_time_sec =
+            finite_or(self.day_time_sec, default.day_time_sec).max(MIN_CYCLE_HALF_SEC);
+        let night_time_sec =
+            finite_or(self.night_time_sec, default.night_time_sec).max(MIN_CYCLE_HALF_SEC);
+        let mut sunrise_time_sec =
+            finite_or(self.sunrise_time_sec, default.sunrise_time_sec).max(0.0);
+        let mut sunset_time_sec = finite_or(self.sunset_time_sec, default.sunset_time_sec).max(0.0);
+
+        let transitions = sunrise_time_sec + sunset_time_sec;
+        let max_transitions = day_time_sec.min(night_time_sec);
+        if transitions > max_transitions {
+            let scale = max_transitions / transitions;
+            sunrise_time_sec *= scale;
+            sunset_time_sec *= scale;
+        }
+        Self {
+            day_time_sec,
+            night_time_sec,
+            sunrise_time_sec,
+            sunset_time_sec,
+        }
+    }
+
     #[inline]
     pub fn day_percent(&self, time: f32) -> f32 {
         (time / self.day_time_sec).min(1.0)
@@ -124,6 +562,110 @@ impl SkyTimeSettings {
             + self.night_percent(time) * std::f32::consts::PI
     }
 
+    #[inline]
+    /// convert sky time into wall-clock hours 0.0 -> 24.0
+    /// time 0 is [`SUNRISE_HOUR`], and the end of the day is [`SUNSET_HOUR`]
+    pub fn hours(&self, time: f32) -> f32 {
+        (SUNRISE_HOUR + self.time_percent(time) * 24.0).rem_euclid(24.0)
+    }
+
+    #[inline]
+    /// convert wall-clock hours 0.0 -> 24.0 into sky time, inverse of [`Self::hours`]
+    pub fn time_from_hours(&self, hours: f32) -> f32 {
+        self.time_from_percent((hours - SUNRISE_HOUR).rem_euclid(24.0) / 24.0)
+    }
+
+    #[inline]
+    /// convert a cycle percent 0.0 -> 1.0 into sky time, inverse of [`Self::time_percent`]
+    pub fn time_from_percent(&self, percent: f32) -> f32 {
+        if percent <= 0.5 {
+            percent * 2.0 * self.day_time_sec
+        } else {
+            self.day_time_sec + (percent - 0.5) * 2.0 * self.night_time_sec
+        }
+    }
+
+    /// which phase the day is in at `time`, see [`DayPhase`]
+    pub fn phase(&self, time: f32) -> DayPhase {
+        self.phase_progress(time).0
+    }
+
+    /// which phase the day is in at `time`, and how far into that phase 0.0 -> 1.0
+    pub fn phase_progress(&self, time: f32) -> (DayPhase, f32) {
+        let total_time = self.total_time();
+        let sunrise_end = self.sunrise_time_sec;
+        let sunset_start = self.day_time_sec - self.sunset_time_sec;
+        let sunset_end = self.day_time_sec + self.sunset_time_sec;
+        let sunrise_start = total_time - self.sunrise_time_sec;
+        let progress = |start: f32, end: f32| {
+            ((time - start) / (end - start).max(f32::EPSILON)).clamp(0.0, 1.0)
+        };
+
+        if time < sunrise_end {
+            // the sunrise started at the end of the previous cycle
+            (
+                DayPhase::Sunrise,
+                progress(-self.sunrise_time_sec, sunrise_end),
+            )
+        } else if time < sunset_start {
+            (DayPhase::Day, progress(sunrise_end, sunset_start))
+        } else if time < sunset_end {
+            (DayPhase::Sunset, progress(sunset_start, sunset_end))
+        } else if time < sunrise_start {
+            (DayPhase::Night, progress(sunset_end, sunrise_start))
+        } else {
+            (
+                DayPhase::Sunrise,
+                progress(sunrise_start, total_time + sunrise_end),
+            )
+        }
+    }
+
+    /// the phase changes between two `(SkyTime.day, SkyTime.time)` points.
+    /// moving forward less than a day reports every boundary crossed, in order.
+    /// moving backwards, or a day or more forward, reports a single change from the phase
+    /// at `previous` to the phase at `current`, skipping the phases in between
+    pub fn phase_changes(&self, previous: (u32, f32), current: (u32, f32)) -> Vec<SkyPhaseChanged> {
+        let (previous_day, previous_time) = previous;
+        let (day, time) = current;
+        // (day, time) pairs instead of day * total_time + time, that loses precision after many days
+        let a_day_later = (previous_day.saturating_add(1), previous_time);
+        if current < previous || current >= a_day_later {
+            let (from, to) = (self.phase(previous_time), self.phase(time));
+            return if from == to {
+                Vec::new()
+            } else {
+                vec![SkyPhaseChanged { from, to, day }]
+            };
+        }
+
+        // walk forward through every boundary crossed, including wrapping into the next day
+        let mut changes = Vec::new();
+        for boundary_day in previous_day..=day {
+            for (boundary_time, phase) in self.phase_boundaries() {
+                let boundary = (boundary_day, boundary_time);
+                if boundary > previous && boundary <= current {
+                    changes.push(SkyPhaseChanged {
+                        from: phase,
+                        to: phase.next(),
+                        day: boundary_day,
+                    });
+                }
+            }
+        }
+        changes
+    }
+
+    /// the time each phase ends, in the order they happen within a cycle
+    pub fn phase_boundaries(&self) -> [(f32, DayPhase); 4] {
+        [
+            (self.sunrise_time_sec, DayPhase::Sunrise),
+            (self.day_time_sec - self.sunset_time_sec, DayPhase::Day),
+            (self.day_time_sec + self.sunset_time_sec, DayPhase::Sunset),
+            (self.total_time() - self.sunrise_time_sec, DayPhase::Night),
+        ]
+    }
+
     #[inline]
     pub fn total_time(&self) -> f32 {
         self.day_time_sec + self.night_time_sec
@@ -146,3 +688,158 @@ impl SkyTimeSettings {
         self.sunset_time_sec / self.night_time_sec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(from: DayPhase, to: DayPhase, day: u32) -> SkyPhaseChanged {
+        SkyPhaseChanged { from, to, day }
+    }
+
+    fn assert_time(sky_time: &SkyTime, day: u32, time: f32) {
+        assert_eq!(sky_time.day, day);
+        assert!(
+            (sky_time.time - time).abs() < 1e-4,
+            "{} != {time}",
+            sky_time.time
+        );
+    }
+
+    // a default day is 40 seconds
+    #[test]
+    fn tick_speed_and_pause() {
+        let settings = SkyTimeSettings::default();
+        let mut sky_time = SkyTime {
+            speed: 2.0,
+            ..default()
+        };
+        sky_time.tick(1.5, &settings);
+        assert_time(&sky_time, 0, 3.0);
+
+        sky_time.speed = 0.0;
+        sky_time.tick(1.0, &settings);
+        assert_time(&sky_time, 0, 3.0);
+
+        sky_time.speed = 1.0;
+        sky_time.pause();
+        sky_time.tick(1.0, &settings);
+        assert_time(&sky_time, 0, 3.0);
+        sky_time.resume();
+
+        // a large delta skips whole days
+        sky_time.tick(85.0, &settings);
+        assert_time(&sky_time, 2, 8.0);
+        // backwards into the previous day
+        sky_time.speed = -1.0;
+        sky_time.tick(10.0, &settings);
+        assert_time(&sky_time, 1, 38.0);
+    }
+
+    #[test]
+    fn transition_eases_to_the_target() {
+        let settings = SkyTimeSettings::default();
+        let mut sky_time = SkyTime {
+            // the transition owns the time, speed is ignored
+            speed: 3.0,
+            ..default()
+        };
+        sky_time.transition_to(&settings, 10.0, 2.0);
+        // smoothstep: a quarter of the duration is 0.15625 of the distance
+        sky_time.tick(0.5, &settings);
+        assert_time(&sky_time, 0, 1.5625);
+        sky_time.tick(0.5, &settings);
+        assert_time(&sky_time, 0, 5.0);
+        assert!(sky_time.is_transitioning());
+        sky_time.tick(1.0, &settings);
+        assert_time(&sky_time, 0, 10.0);
+        assert!(!sky_time.is_transitioning());
+
+        // paused, the transition waits too
+        sky_time.transition_to(&settings, 20.0, 1.0);
+        sky_time.pause();
+        sky_time.tick(1.0, &settings);
+        assert_time(&sky_time, 0, 10.0);
+        assert!(sky_time.is_transitioning());
+    }
+
+    #[test]
+    fn transition_wraps_into_the_next_day() {
+        let settings = SkyTimeSettings::default();
+        let mut sky_time = SkyTime {
+            time: 35.0,
+            ..default()
+        };
+        // 5.0 is earlier than 35.0, so the target is tomorrow, 10 seconds away
+        sky_time.transition_to(&settings, 5.0, 2.0);
+        sky_time.tick(0.5, &settings);
+        assert_time(&sky_time, 0, 36.5625);
+        sky_time.tick(1.0, &settings);
+        assert_time(&sky_time, 1, 3.4375);
+        sky_time.tick(0.5, &settings);
+        assert_time(&sky_time, 1, 5.0);
+        assert!(!sky_time.is_transitioning());
+    }
+
+    #[test]
+    fn transition_step_sums_to_the_distance() {
+        let mut transition = SkyTimeTransition::new(7.0, 3.0);
+        let total: f32 = (0..12).map(|_| transition.step(0.25)).sum();
+        assert!((total - 7.0).abs() < 1e-4);
+        assert!(transition.is_finished());
+        // finished, no more steps
+        assert_eq!(transition.step(0.25), 0.0);
+
+        let mut instant = SkyTimeTransition::new(4.0, 0.0);
+        assert_eq!(instant.step(0.016), 4.0);
+        assert!(instant.is_finished());
+    }
+
+    // default boundaries: sunrise ends at 2, day at 13, sunset at 17, night at 38. a day is 40
+    #[test]
+    fn phase_changes() {
+        use DayPhase::*;
+        let settings = SkyTimeSettings::default();
+        let cases = [
+            // nothing crossed
+            ((0, 5.0), (0, 6.0), vec![]),
+            ((0, 1.9), (0, 2.1), vec![change(Sunrise, Day, 0)]),
+            // landing exactly on a boundary counts, leaving it does not
+            ((0, 1.0), (0, 2.0), vec![change(Sunrise, Day, 0)]),
+            ((0, 2.0), (0, 3.0), vec![]),
+            // several boundaries in one frame
+            (
+                (0, 12.0),
+                (0, 18.0),
+                vec![change(Day, Sunset, 0), change(Sunset, Night, 0)],
+            ),
+            // wraps around into the next day
+            (
+                (0, 37.5),
+                (1, 2.5),
+                vec![change(Night, Sunrise, 0), change(Sunrise, Day, 1)],
+            ),
+            // far into the game, day * total_time would be too large for f32
+            (
+                (1_000_000, 1.99),
+                (1_000_000, 2.01),
+                vec![change(Sunrise, Day, 1_000_000)],
+            ),
+            ((1_000_000, 39.99), (1_000_001, 0.01), vec![]),
+            // skipping days reports where we ended up
+            ((0, 5.0), (3, 15.0), vec![change(Day, Sunset, 3)]),
+            ((0, 5.0), (1, 5.0), vec![]),
+            // so does going backwards
+            ((2, 15.0), (2, 5.0), vec![change(Sunset, Day, 2)]),
+            ((2, 15.0), (1, 30.0), vec![change(Sunset, Night, 1)]),
+            ((2, 6.0), (2, 5.0), vec![]),
+        ];
+        for (previous, current, expected) in cases {
+            assert_eq!(
+                settings.phase_changes(previous, current),
+                expected,
+                "{previous:?} -> {current:?}"
+            );
+        }
+    }
+}
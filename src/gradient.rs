@@ -1,62 +1,128 @@
-use crate::cycle::SkyTimeSettings;
-use bevy::prelude::*;
+use crate::{
+    ambient_driver::ScalarGradientBuilder,
+    bind_groups::{GradientBindGroup, GroundBindGroup},
+    cycle::SkyTimeSettings,
+    gradient_material::FullGradientMaterial,
+    keyframes::KeyframeGradientBuilder,
+};
+use bevy::{
+    math::curve::{Curve, Interval},
+    prelude::*,
+};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
 /// All the current colors that controlls the sky gradient
-/// a sky gradient has 4 colors, and we animate it based upon the "sky time"
+/// a sky gradient has any number of colors, and we animate them based upon the "sky time"
 /// gradient stops 0.0 -> 0.5 = DAY time colors
 /// gradient stops 0.5 -> 1.0 = NIGHT time colors
 /// Use the SkyColorsBuilder to easily construct the colors from variables like: day_color, night_color
-#[derive(Resource)]
+/// also a component, used by a camera with its own [`crate::instance::SkyInstance`]
+#[derive(Resource, Component, Clone, Default)]
 pub struct SkyGradients {
-    pub sky_color0: Gradient,
-    pub sky_color1: Gradient,
-    pub sky_color2: Gradient,
-    pub sky_color3: Gradient,
+    /// one gradient per sky color, from the bottom to the top of the sky
+    pub sky_colors: Vec<Gradient>,
+    /// 0.0 -> 1.0, where each sky color is positioned over the day, one per sky color
+    pub positions: Vec<ScalarGradient>,
 }
 
-impl Clone for SkyGradients {
-    fn clone(&self) -> Self {
-        Self {
-            sky_color0: Gradient {
-                stops: self.sky_color0.stops.clone(),
-            },
-            sky_color1: Gradient {
-                stops: self.sky_color1.stops.clone(),
-            },
-            sky_color2: Gradient {
-                stops: self.sky_color2.stops.clone(),
-            },
-            sky_color3: Gradient {
-                stops: self.sky_color3.stops.clone(),
-            },
+impl SkyGradients {
+    /// sample every gradient at the cycle `percent`, and write the colors into the bind group
+    pub fn sample_into(&self, percent: f32, gradient_bind_group: &mut GradientBindGroup) {
+        let stops = self.sample_stops(percent);
+        gradient_bind_group.color_stops.clear();
+        gradient_bind_group
+            .color_stops
+            .extend(stops.iter().map(|(_, color)| Vec4::from(*color)));
+        gradient_bind_group.positions.clear();
+        gradient_bind_group
+            .positions
+            .extend(stops.iter().map(|(position, _)| *position));
+    }
+
+    /// the bind group sampled at the cycle `percent`, None if it equals `current`.
+    /// lets drivers skip `Assets::get_mut`, which marks the material as modified
+    pub fn sample_changed(
+        &self,
+        percent: f32,
+        current: &GradientBindGroup,
+    ) -> Option<GradientBindGroup> {
+        let mut sampled = GradientBindGroup {
+            color_stops: Vec::new(),
+            positions: Vec::new(),
+        };
+        self.sample_into(percent, &mut sampled);
+        (sampled != *current).then_some(sampled)
+    }
+
+    /// (position, color) of every sky color at the cycle `percent`, sorted by position
+    pub fn sample_stops(&self, percent: f32) -> Vec<(f32, [f32; 4])> {
+        let mut stops: Vec<(f32, [f32; 4])> = self
+            .positions
+            .iter()
+            .zip(&self.sky_colors)
+            .map(|(position, sky_color)| {
+                (position.sample_at(percent), sky_color.sample_at(percent))
+            })
+            .collect();
+        // animated positions can cross each other, the shader expects them in order
+        stops.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        stops
+    }
+
+    /// the sky color at `height` 0.0 (straight down) -> 1.0 (straight up), given the sampled stops
+    pub fn color_at_height(stops: &[(f32, [f32; 4])], height: f32) -> [f32; 4] {
+        let Some((_, first)) = stops.first() else {
+            return [0.0; 4];
+        };
+        let idx = stops.partition_point(|(position, _)| *position < height);
+        if idx == 0 {
+            return *first;
+        }
+        if idx >= stops.len() {
+            return stops.last().unwrap().1;
         }
+        let ((p0, c0), (p1, c1)) = (stops[idx - 1], stops[idx]);
+        let ratio = ((height - p0) / (p1 - p0).max(0.00001)).clamp(0.0, 1.0);
+        std::array::from_fn(|i| c0[i].lerp(c1[i], ratio))
     }
-}
 
-impl Default for SkyGradients {
-    fn default() -> Self {
-        Self {
-            sky_color0: Default::default(),
-            sky_color1: Default::default(),
-            sky_color2: Default::default(),
-            sky_color3: Default::default(),
+    /// rgba8 lookup texture data of the whole day, x = cycle percent 0.0 -> 1.0, y = height 1.0 (top row) -> 0.0
+    pub fn bake_lut(&self, cycle_resolution: u32, height_resolution: u32) -> Vec<u8> {
+        self.bake_lut_colors(cycle_resolution, height_resolution)
+            .into_iter()
+            .flatten()
+            .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect()
+    }
+
+    /// same layout as [`Self::bake_lut`], one color per texel, HDR colors can go above 1.0
+    pub fn bake_lut_colors(&self, cycle_resolution: u32, height_resolution: u32) -> Vec<[f32; 4]> {
+        let (width, height) = (cycle_resolution.max(2), height_resolution.max(2));
+        let mut data = vec![[0.0; 4]; (width * height) as usize];
+        for x in 0..width {
+            let percent = x as f32 / (width - 1) as f32;
+            let stops = self.sample_stops(percent);
+            for y in 0..height {
+                data[(y * width + x) as usize] =
+                    Self::color_at_height(&stops, 1.0 - y as f32 / (height - 1) as f32);
+            }
         }
+        data
     }
 }
 
 ///! day/night time specific colors mapped onto a gradient from SkyTimeSettings
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Reflect)]
+#[derive(Clone, Default, Reflect)]
 pub struct GradientBuilder {
-    pub sunrise_color: [u8; 4],
-    pub day_low_color: [u8; 4],
-    pub day_high_color: [u8; 4],
-    pub sunset_color: [u8; 4],
-    pub night_low_color: [u8; 4],
-    pub night_high_color: [u8; 4],
+    pub sunrise_color: GradientColor,
+    pub day_low_color: GradientColor,
+    pub day_high_color: GradientColor,
+    pub sunset_color: GradientColor,
+    pub night_low_color: GradientColor,
+    pub night_high_color: GradientColor,
 }
 
 impl GradientBuilder {
@@ -78,57 +144,540 @@ impl GradientBuilder {
             (1.0, self.sunrise_color),
         ])
     }
+
+    /// blend every color towards `other`, t: 0.0 = self, 1.0 = other
+    pub fn lerp(&self, other: &GradientBuilder, t: f32) -> GradientBuilder {
+        let lerp = |a: GradientColor, b: GradientColor| a.lerp(b, t);
+        GradientBuilder {
+            sunrise_color: lerp(self.sunrise_color, other.sunrise_color),
+            day_low_color: lerp(self.day_low_color, other.day_low_color),
+            day_high_color: lerp(self.day_high_color, other.day_high_color),
+            sunset_color: lerp(self.sunset_color, other.sunset_color),
+            night_low_color: lerp(self.night_low_color, other.night_low_color),
+            night_high_color: lerp(self.night_high_color, other.night_high_color),
+        }
+    }
+}
+
+/// one color of the sky, animated over the day
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Reflect)]
+pub struct SkyGradientStop {
+    /// 0.0 -> 1.0, where on the sky the color is. 0.0 = straight down, 1.0 = straight up
+    pub position: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub colors: GradientBuilder,
+    /// if set, replaces `colors` with any number of named keyframes over the day
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub keyframes: Option<KeyframeGradientBuilder>,
+    /// if set, replaces `position` with a position per time of day.
+    /// a thick sunset band that thins out at noon for example
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub animated_position: Option<ScalarGradientBuilder>,
+}
+
+impl SkyGradientStop {
+    /// where the color is over the day, constant unless `animated_position` is set
+    pub fn position_builder(&self) -> ScalarGradientBuilder {
+        self.animated_position
+            .clone()
+            .unwrap_or_else(|| ScalarGradientBuilder::constant(self.position))
+    }
+
+    /// the color over the day, from `keyframes` if set
+    pub fn build_colors(&self, s: &SkyTimeSettings) -> Gradient {
+        match &self.keyframes {
+            Some(keyframes) => keyframes.build_gradient(s),
+            None => self.colors.build_gradient(s),
+        }
+    }
+
+    pub fn build_position(&self, s: &SkyTimeSettings) -> ScalarGradient {
+        match &self.animated_position {
+            Some(animated_position) => animated_position.build_gradient(s),
+            None => ScalarGradient::new(vec![(0.0, self.position)]),
+        }
+    }
 }
 
 /// helper for designing gradients based upon time settings
 /// if we want specific time of day colors. like "day_high_color"
 /// the helper helps distribute these colors over a gradient based upon the SkyTimeSettings
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Resource)]
+#[cfg_attr(feature = "serde", serde(from = "SkyGradientBuilderFile"))]
+#[derive(Clone, Resource, Asset, TypePath)]
 pub struct SkyGradientBuilder {
-    pub gradient_builder_stop0: GradientBuilder,
-    pub gradient_builder_stop1: GradientBuilder,
-    pub gradient_builder_stop2: GradientBuilder,
-    pub gradient_builder_stop3: GradientBuilder,
+    /// any number of sky colors, in any order
+    pub stops: Vec<SkyGradientStop>,
+    /// how each sky color blends over the day
+    pub interpolation: Interpolation,
+    /// which color space each sky color blends in over the day
+    pub color_space: ColorSpace,
 }
 
 impl Default for SkyGradientBuilder {
     fn default() -> Self {
-        crate::presets::DEFAULT_SKY_COLORS_BUILDER
+        Self::new(crate::presets::DEFAULT_SKY_GRADIENT_STOPS)
     }
 }
 
 impl SkyGradientBuilder {
+    pub fn new(stops: impl Into<Vec<SkyGradientStop>>) -> Self {
+        Self {
+            stops: stops.into(),
+            interpolation: Interpolation::default(),
+            color_space: ColorSpace::default(),
+        }
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
     pub fn build(&self, sky_time_settings: &SkyTimeSettings) -> SkyGradients {
+        let mut stops: Vec<&SkyGradientStop> = self.stops.iter().collect();
+        // the shader expects the positions in order
+        stops.sort_by(|a, b| {
+            a.position
+                .partial_cmp(&b.position)
+                .unwrap_or(Ordering::Equal)
+        });
         SkyGradients {
-            sky_color0: self
-                .gradient_builder_stop0
-                .build_gradient(&sky_time_settings),
-            sky_color1: self
-                .gradient_builder_stop1
-                .build_gradient(&sky_time_settings),
-            sky_color2: self
-                .gradient_builder_stop2
-                .build_gradient(&sky_time_settings),
-            sky_color3: self
-                .gradient_builder_stop3
-                .build_gradient(&sky_time_settings),
+            sky_colors: stops
+                .iter()
+                .map(|stop| {
+                    stop.build_colors(sky_time_settings)
+                        .with_interpolation(self.interpolation)
+                        .with_color_space(self.color_space)
+                })
+                .collect(),
+            positions: stops
+                .iter()
+                .map(|stop| stop.build_position(sky_time_settings))
+                .collect(),
+        }
+    }
+
+    /// blend every color and position towards `other`, t: 0.0 = self, 1.0 = other
+    /// if the stop counts differ, there is nothing to blend, the closest one of the two is returned.
+    /// interpolation and color space come from the closest one
+    pub fn lerp(&self, other: &SkyGradientBuilder, t: f32) -> SkyGradientBuilder {
+        let closest = if t < 0.5 { self } else { other };
+        if self.stops.len() != other.stops.len() {
+            return closest.clone();
+        }
+        SkyGradientBuilder {
+            interpolation: closest.interpolation,
+            color_space: closest.color_space,
+            stops: self
+                .stops
+                .iter()
+                .zip(&other.stops)
+                .map(|(a, b)| SkyGradientStop {
+                    position: a.position.lerp(b.position, t),
+                    colors: a.colors.lerp(&b.colors, t),
+                    // keyframes can't be blended, they may have nothing in common
+                    keyframes: if t < 0.5 {
+                        a.keyframes.clone()
+                    } else {
+                        b.keyframes.clone()
+                    },
+                    animated_position: (a.animated_position.is_some()
+                        || b.animated_position.is_some())
+                    .then(|| a.position_builder().lerp(&b.position_builder(), t)),
+                })
+                .collect(),
         }
     }
 }
 
-/// A color gradient with linear interpolation.
+/// colors below the horizon, with their own day cycle.
+/// stop positions: 0.0 = straight down, 1.0 = at the horizon
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug, PartialEq, Reflect)]
+#[derive(Clone, Resource, Asset, TypePath)]
+pub struct GroundGradientBuilder {
+    pub colors: SkyGradientBuilder,
+    pub horizon: GroundBindGroup,
+}
+
+impl Default for GroundGradientBuilder {
+    fn default() -> Self {
+        Self {
+            colors: SkyGradientBuilder::new(crate::presets::DEFAULT_GROUND_GRADIENT_STOPS),
+            horizon: GroundBindGroup::default(),
+        }
+    }
+}
+
+impl GroundGradientBuilder {
+    pub fn build(&self, sky_time_settings: &SkyTimeSettings) -> GroundGradients {
+        GroundGradients {
+            colors: self.colors.build(sky_time_settings),
+            horizon: self.horizon.clone(),
+        }
+    }
+}
+
+/// the current ground colors, built by [`GroundGradientBuilder`]
+#[derive(Resource, Clone, Default)]
+pub struct GroundGradients {
+    pub colors: SkyGradients,
+    pub horizon: GroundBindGroup,
+}
+
+impl GroundGradients {
+    /// sample the ground colors at the cycle `percent`, and turn the ground on in the material
+    pub fn sample_into(&self, percent: f32, gradient_material: &mut FullGradientMaterial) {
+        self.colors
+            .sample_into(percent, &mut gradient_material.ground_gradient_bind_group);
+        gradient_material.ground.clone_from(&self.horizon);
+        gradient_material.feature_ground_enabled = 1;
+    }
+
+    /// blend the ground into colors baked by [`SkyGradients::bake_lut_colors`], like the shader does
+    pub fn bake_into_lut(
+        &self,
+        lut: &mut [[f32; 4]],
+        cycle_resolution: u32,
+        height_resolution: u32,
+    ) {
+        let (width, height) = (cycle_resolution.max(2), height_resolution.max(2));
+        for x in 0..width {
+            let percent = x as f32 / (width - 1) as f32;
+            let stops = self.colors.sample_stops(percent);
+            for y in 0..height {
+                // the lut height is gradient_t, turn it back into the view height
+                let view_y = (1.0 - y as f32 / (height - 1) as f32) * 2.0 - 1.0;
+                let ground = SkyGradients::color_at_height(&stops, self.horizon.ground_t(view_y));
+                let blend = self.horizon.ground_blend(view_y);
+                let texel = &mut lut[(y * width + x) as usize];
+                *texel = std::array::from_fn(|i| ground[i].lerp(texel[i], blend));
+            }
+        }
+    }
+}
+
+/// SkyGradientBuilder as stored in files,
+/// presets saved when the sky had exactly 4 colors use gradient_builder_stop0..3
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+pub(crate) struct SkyGradientBuilderFile {
+    #[serde(default)]
+    stops: Vec<SkyGradientStop>,
+    #[serde(default)]
+    interpolation: Interpolation,
+    #[serde(default)]
+    color_space: ColorSpace,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    gradient_builder_stop0: Option<GradientBuilder>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    gradient_builder_stop1: Option<GradientBuilder>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    gradient_builder_stop2: Option<GradientBuilder>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    gradient_builder_stop3: Option<GradientBuilder>,
+}
+
+#[cfg(feature = "serde")]
+impl SkyGradientBuilderFile {
+    /// the 4 legacy colors were positioned by the GradientBindGroup saved next to them.
+    /// without `legacy_positions`, the default GradientBindGroup positions are used
+    pub(crate) fn into_builder(self, legacy_positions: Option<&[f32]>) -> SkyGradientBuilder {
+        let stops = if !self.stops.is_empty() {
+            self.stops
+        } else {
+            let legacy_stops = [
+                self.gradient_builder_stop0,
+                self.gradient_builder_stop1,
+                self.gradient_builder_stop2,
+                self.gradient_builder_stop3,
+            ];
+            let legacy_positions = match legacy_positions {
+                Some(positions) if positions.len() >= legacy_stops.len() => positions.to_vec(),
+                _ => GradientBindGroup::default().positions,
+            };
+            legacy_stops
+                .into_iter()
+                .zip(legacy_positions)
+                .filter_map(|(colors, position)| {
+                    Some(SkyGradientStop {
+                        position,
+                        colors: colors?,
+                        keyframes: None,
+                        animated_position: None,
+                    })
+                })
+                .collect()
+        };
+        SkyGradientBuilder::new(stops)
+            .with_interpolation(self.interpolation)
+            .with_color_space(self.color_space)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SkyGradientBuilderFile> for SkyGradientBuilder {
+    fn from(file: SkyGradientBuilderFile) -> Self {
+        file.into_builder(None)
+    }
+}
+
+// legacy fields are written without Some(..)
+#[cfg(feature = "serde")]
+fn deserialize_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// a gradient color, plain sRGB or HDR
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum GradientColor {
+    /// 0 -> 255 sRGB
+    Srgb([u8; 4]),
+    /// linear color, channels above 1.0 glow with bloom and exposure.
+    /// the sky cameras need [`crate::plugin::SkySettings::hdr`] to keep them above 1.0
+    Hdr(LinearRgba),
+}
+
+impl Default for GradientColor {
+    fn default() -> Self {
+        GradientColor::Srgb([0, 0, 0, 255])
+    }
+}
+
+impl From<[u8; 4]> for GradientColor {
+    fn from(color: [u8; 4]) -> Self {
+        GradientColor::Srgb(color)
+    }
+}
+
+impl From<LinearRgba> for GradientColor {
+    fn from(color: LinearRgba) -> Self {
+        GradientColor::Hdr(color)
+    }
+}
+
+impl From<Color> for GradientColor {
+    fn from(color: Color) -> Self {
+        GradientColor::Hdr(color.to_linear())
+    }
+}
+
+impl GradientColor {
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, GradientColor::Hdr(_))
+    }
+
+    pub fn to_srgba(&self) -> Srgba {
+        match *self {
+            GradientColor::Srgb([r, g, b, a]) => Srgba::rgba_u8(r, g, b, a),
+            GradientColor::Hdr(linear) => linear.into(),
+        }
+    }
+
+    pub fn to_linear(&self) -> LinearRgba {
+        match *self {
+            GradientColor::Srgb(_) => self.to_srgba().into(),
+            GradientColor::Hdr(linear) => linear,
+        }
+    }
+
+    /// t: 0.0 = self, 1.0 = other. stays sRGB u8 unless one of them is HDR
+    pub fn lerp(&self, other: GradientColor, t: f32) -> GradientColor {
+        match (*self, other) {
+            (GradientColor::Srgb(a), GradientColor::Srgb(b)) => {
+                GradientColor::Srgb(std::array::from_fn(|i| {
+                    (a[i] as f32).lerp(b[i] as f32, t).round() as u8
+                }))
+            }
+            _ => GradientColor::Hdr(self.to_linear().mix(&other.to_linear(), t)),
+        }
+    }
+}
+
+// sRGB colors are stored as plain (r, g, b, a) tuples, like before HDR colors existed
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum GradientColorText {
+    Srgb([u8; 4]),
+    Hdr(LinearRgba),
+}
+
+// compact formats like bincode can't tell the two apart without a tag
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+enum GradientColorBinary {
+    Srgb([u8; 4]),
+    Hdr(LinearRgba),
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for GradientColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (*self, serializer.is_human_readable()) {
+            (GradientColor::Srgb(color), true) => {
+                GradientColorText::Srgb(color).serialize(serializer)
+            }
+            (GradientColor::Hdr(color), true) => {
+                GradientColorText::Hdr(color).serialize(serializer)
+            }
+            (GradientColor::Srgb(color), false) => {
+                GradientColorBinary::Srgb(color).serialize(serializer)
+            }
+            (GradientColor::Hdr(color), false) => {
+                GradientColorBinary::Hdr(color).serialize(serializer)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for GradientColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(match GradientColorText::deserialize(deserializer)? {
+                GradientColorText::Srgb(color) => GradientColor::Srgb(color),
+                GradientColorText::Hdr(color) => GradientColor::Hdr(color),
+            })
+        } else {
+            Ok(match GradientColorBinary::deserialize(deserializer)? {
+                GradientColorBinary::Srgb(color) => GradientColor::Srgb(color),
+                GradientColorBinary::Hdr(color) => GradientColor::Hdr(color),
+            })
+        }
+    }
+}
+
+/// how values are blended between two gradient stops
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub enum Interpolation {
+    /// keep the value of the previous stop, until the next stop is reached
+    Step,
+    #[default]
+    Linear,
+    /// eases in and out of every stop
+    SmoothStep,
+    /// smooth curve passing through every stop, may overshoot between stops
+    CatmullRom,
+}
+
+impl Interpolation {
+    /// blend from `p1` to `p2`, t: 0.0 -> 1.0.
+    /// `p0` and `p3` are the stops before and after, only used by CatmullRom
+    pub fn interpolate(&self, p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+        match self {
+            Interpolation::Step => {
+                if t >= 1.0 {
+                    p2
+                } else {
+                    p1
+                }
+            }
+            Interpolation::Linear => p1.lerp(p2, t),
+            Interpolation::SmoothStep => p1.lerp(p2, t * t * (3.0 - 2.0 * t)),
+            Interpolation::CatmullRom => {
+                let t2 = t * t;
+                let t3 = t2 * t;
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+            }
+        }
+    }
+}
+
+/// which color space gradient colors are blended in.
+/// sampled colors are always returned as sRGB
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub enum ColorSpace {
+    /// blend the stored values as is, cheapest, but midpoints can look muddy
+    #[default]
+    Srgb,
+    /// physically correct light blending, midpoints look brighter
+    Linear,
+    /// perceptually uniform, keeps midpoints between different hues vivid
+    Oklab,
+}
+
+impl ColorSpace {
+    /// gradient color into this color space.
+    /// HDR colors are already linear, they only go through the sRGB transfer for Srgb
+    pub fn encode(&self, color: GradientColor) -> [f32; 4] {
+        match self {
+            ColorSpace::Srgb => color.to_srgba().to_f32_array(),
+            ColorSpace::Linear => color.to_linear().to_f32_array(),
+            ColorSpace::Oklab => {
+                let oklaba = Oklaba::from(color.to_linear());
+                [oklaba.lightness, oklaba.a, oklaba.b, oklaba.alpha]
+            }
+        }
+    }
+
+    /// color in this color space back into sRGB, HDR colors can go above 1.0
+    pub fn decode(&self, color: [f32; 4]) -> [f32; 4] {
+        let [c0, c1, c2, alpha] = color;
+        let srgba = match self {
+            ColorSpace::Srgb => Srgba::new(c0, c1, c2, alpha),
+            ColorSpace::Linear => LinearRgba::new(c0, c1, c2, alpha).into(),
+            ColorSpace::Oklab => Oklaba::new(c0, c1, c2, alpha).into(),
+        };
+        let [r, g, b, a] = srgba.to_f32_array();
+        [r.max(0.0), g.max(0.0), b.max(0.0), a.clamp(0.0, 1.0)]
+    }
+}
+
+/// A color gradient, with selectable interpolation and color space.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Reflect, Asset)]
 pub struct Gradient {
-    pub stops: Vec<(f32, [u8; 4])>,
+    pub stops: Vec<(f32, GradientColor)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interpolation: Interpolation,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub color_space: ColorSpace,
 }
 
 impl Gradient {
     /// Create a new gradient. Stops are automatically sorted by position.
-    pub fn new(mut stops: Vec<(f32, [u8; 4])>) -> Self {
-        stops.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-        Self { stops }
+    pub fn new<C: Into<GradientColor>>(stops: Vec<(f32, C)>) -> Self {
+        let mut gradient = Self {
+            stops: stops
+                .into_iter()
+                .map(|(position, color)| (position, color.into()))
+                .collect(),
+            interpolation: Interpolation::default(),
+            color_space: ColorSpace::default(),
+        };
+        gradient.sort();
+        gradient
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// true if any stop is an HDR color
+    pub fn is_hdr(&self) -> bool {
+        self.stops.iter().any(|(_, color)| color.is_hdr())
     }
 
     /// Sort the stops. Call this if you manually modify the `stops` vector.
@@ -138,7 +687,8 @@ impl Gradient {
     }
 
     /// Sample the gradient at position `t` (0.0 to 1.0).
-    /// Returns a normalized color [f32; 4] where channels are 0.0 to 1.0.
+    /// Returns a normalized sRGB color [f32; 4] where channels are 0.0 to 1.0,
+    /// HDR stops can go above 1.0.
     pub fn sample_at(&self, t: f32) -> [f32; 4] {
         if self.stops.is_empty() {
             return [0.0, 0.0, 0.0, 1.0];
@@ -146,36 +696,122 @@ impl Gradient {
 
         // Find insertion point
         let idx = self.stops.partition_point(|(x, _)| *x < t);
-
-        // Helper to convert u8 [0-255] to f32 [0.0-1.0]
-        let to_f32 = |c: [u8; 4]| {
-            [
-                c[0] as f32 / 255.0,
-                c[1] as f32 / 255.0,
-                c[2] as f32 / 255.0,
-                c[3] as f32 / 255.0,
-            ]
-        };
+        let to_f32 = |c: GradientColor| ColorSpace::Srgb.encode(c);
         if idx == 0 {
             return to_f32(self.stops[0].1);
         }
         if idx >= self.stops.len() {
             return to_f32(self.stops.last().unwrap().1);
         }
-        let (t0, c0_u8) = self.stops[idx - 1];
-        let (t1, c1_u8) = self.stops[idx];
-        let c0 = to_f32(c0_u8);
-        let c1 = to_f32(c1_u8);
-        // Linear interpolation
-        let ratio = ((t - t0) / (t1 - t0)).clamp(0.0, 1.0);
-        let lerp = |a: f32, b: f32| a + (b - a) * ratio;
 
-        [
-            lerp(c0[0], c1[0]),
-            lerp(c0[1], c1[1]),
-            lerp(c0[2], c1[2]),
-            lerp(c0[3], c1[3]),
-        ]
+        let (t1, _) = self.stops[idx - 1];
+        let (t2, _) = self.stops[idx];
+        let ratio = ((t - t1) / (t2 - t1)).clamp(0.0, 1.0);
+        // neighbouring stops, repeat the first and last stop at the ends
+        let color = |i: usize| {
+            self.color_space
+                .encode(self.stops[i.min(self.stops.len() - 1)].1)
+        };
+        let (c0, c1, c2, c3) = (
+            color(idx.saturating_sub(2)),
+            color(idx - 1),
+            color(idx),
+            color(idx + 1),
+        );
+        let blended = std::array::from_fn(|i| {
+            self.interpolation
+                .interpolate(c0[i], c1[i], c2[i], c3[i], ratio)
+        });
+        let color = self.color_space.decode(blended);
+        if self.is_hdr() {
+            return color;
+        }
+        // overshooting u8 stops shouldn't turn into HDR colors
+        color.map(|channel| channel.min(1.0))
+    }
+
+    /// the sampled color at `t`, as sRGB u8 unless the gradient is HDR
+    pub fn sample_color_at(&self, t: f32) -> GradientColor {
+        let color = self.sample_at(t);
+        if self.is_hdr() {
+            GradientColor::Hdr(Srgba::from_f32_array(color).into())
+        } else {
+            GradientColor::Srgb(Srgba::from_f32_array(color).to_u8_array())
+        }
+    }
+
+    /// insert a stop, after any stops at the same position. returns its index
+    pub fn insert_stop(&mut self, t: f32, color: impl Into<GradientColor>) -> usize {
+        let idx = self.stops.partition_point(|(x, _)| *x <= t);
+        self.stops.insert(idx, (t, color.into()));
+        idx
+    }
+
+    /// insert a stop with the color already at `t`, the gradient looks the same. returns its index
+    pub fn insert_sampled_stop(&mut self, t: f32) -> usize {
+        let color = self.sample_color_at(t);
+        self.insert_stop(t, color)
+    }
+
+    pub fn remove_stop(&mut self, index: usize) -> Option<(f32, GradientColor)> {
+        (index < self.stops.len()).then(|| self.stops.remove(index))
+    }
+
+    /// index of the stop closest to `t`
+    pub fn closest_stop(&self, t: f32) -> Option<usize> {
+        closest_stop(&self.stops, t)
+    }
+
+    /// move a stop to `t`, keeping the stops in order. returns its new index
+    pub fn move_stop(&mut self, index: usize, t: f32) -> Option<usize> {
+        let (_, color) = self.remove_stop(index)?;
+        Some(self.insert_stop(t, color))
+    }
+
+    /// `count` evenly spaced stops from 0.0 to 1.0, sampled from this gradient
+    pub fn resample(&self, count: usize) -> Gradient {
+        Gradient {
+            stops: even_positions(count)
+                .map(|t| (t, self.sample_color_at(t)))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// the gradient flipped, 0.0 <-> 1.0
+    pub fn reversed(&self) -> Gradient {
+        Gradient {
+            stops: reversed_stops(&self.stops),
+            ..self.clone()
+        }
+    }
+
+    /// blend towards `other`, factor: 0.0 = self, 1.0 = other.
+    /// has a stop at every position of both gradients,
+    /// interpolation and color space come from the closest one
+    pub fn blend(&self, other: &Gradient, factor: f32) -> Gradient {
+        let closest = if factor < 0.5 { self } else { other };
+        Gradient {
+            stops: merged_positions(&self.stops, &other.stops)
+                .map(|t| {
+                    let color = self.sample_color_at(t);
+                    (t, color.lerp(other.sample_color_at(t), factor))
+                })
+                .collect(),
+            interpolation: closest.interpolation,
+            color_space: closest.color_space,
+        }
+    }
+}
+
+/// sRGB colors, HDR gradients can go above 1.0
+impl Curve<Srgba> for Gradient {
+    fn domain(&self) -> Interval {
+        Interval::UNIT
+    }
+
+    fn sample_unchecked(&self, t: f32) -> Srgba {
+        Srgba::from_f32_array(self.sample_at(t))
     }
 }
 
@@ -185,19 +821,29 @@ impl Default for Gradient {
     }
 }
 
-/// A scalar gradient with linear interpolation for single float values.
+/// A scalar gradient for single float values, with selectable interpolation.
 /// More memory efficient than Gradient when you only need one channel.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Reflect)]
 pub struct ScalarGradient {
     pub stops: Vec<(f32, f32)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interpolation: Interpolation,
 }
 
 impl ScalarGradient {
     /// Create a new scalar gradient. Stops are automatically sorted by position.
     pub fn new(mut stops: Vec<(f32, f32)>) -> Self {
         stops.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-        Self { stops }
+        Self {
+            stops,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
     }
 
     /// Sort the stops. Call this if you manually modify the `stops` vector.
@@ -219,16 +865,434 @@ impl ScalarGradient {
             return self.stops.last().unwrap().1;
         }
 
-        let (t0, v0) = self.stops[idx - 1];
-        let (t1, v1) = self.stops[idx];
-        // Linear interpolation
-        let ratio = ((t - t0) / (t1 - t0)).clamp(0.0, 1.0);
-        v0 + (v1 - v0) * ratio
+        let (t1, v1) = self.stops[idx - 1];
+        let (t2, v2) = self.stops[idx];
+        let ratio = ((t - t1) / (t2 - t1)).clamp(0.0, 1.0);
+        // neighbouring stops, repeat the first and last stop at the ends
+        let v0 = self.stops[idx.saturating_sub(2)].1;
+        let v3 = self.stops[(idx + 1).min(self.stops.len() - 1)].1;
+        self.interpolation.interpolate(v0, v1, v2, v3, ratio)
+    }
+
+    /// insert a stop, after any stops at the same position. returns its index
+    pub fn insert_stop(&mut self, t: f32, value: f32) -> usize {
+        let idx = self.stops.partition_point(|(x, _)| *x <= t);
+        self.stops.insert(idx, (t, value));
+        idx
+    }
+
+    /// insert a stop with the value already at `t`, the gradient looks the same. returns its index
+    pub fn insert_sampled_stop(&mut self, t: f32) -> usize {
+        self.insert_stop(t, self.sample_at(t))
+    }
+
+    pub fn remove_stop(&mut self, index: usize) -> Option<(f32, f32)> {
+        (index < self.stops.len()).then(|| self.stops.remove(index))
+    }
+
+    /// index of the stop closest to `t`
+    pub fn closest_stop(&self, t: f32) -> Option<usize> {
+        closest_stop(&self.stops, t)
+    }
+
+    /// move a stop to `t`, keeping the stops in order. returns its new index
+    pub fn move_stop(&mut self, index: usize, t: f32) -> Option<usize> {
+        let (_, value) = self.remove_stop(index)?;
+        Some(self.insert_stop(t, value))
+    }
+
+    /// `count` evenly spaced stops from 0.0 to 1.0, sampled from this gradient
+    pub fn resample(&self, count: usize) -> ScalarGradient {
+        ScalarGradient {
+            stops: even_positions(count)
+                .map(|t| (t, self.sample_at(t)))
+                .collect(),
+            interpolation: self.interpolation,
+        }
+    }
+
+    /// the gradient flipped, 0.0 <-> 1.0
+    pub fn reversed(&self) -> ScalarGradient {
+        ScalarGradient {
+            stops: reversed_stops(&self.stops),
+            interpolation: self.interpolation,
+        }
+    }
+
+    /// blend towards `other`, factor: 0.0 = self, 1.0 = other.
+    /// has a stop at every position of both gradients, interpolation comes from the closest one
+    pub fn blend(&self, other: &ScalarGradient, factor: f32) -> ScalarGradient {
+        ScalarGradient {
+            stops: merged_positions(&self.stops, &other.stops)
+                .map(|t| (t, self.sample_at(t).lerp(other.sample_at(t), factor)))
+                .collect(),
+            interpolation: if factor < 0.5 {
+                self.interpolation
+            } else {
+                other.interpolation
+            },
+        }
+    }
+}
+
+impl Curve<f32> for ScalarGradient {
+    fn domain(&self) -> Interval {
+        Interval::UNIT
+    }
+
+    fn sample_unchecked(&self, t: f32) -> f32 {
+        self.sample_at(t)
     }
 }
 
+fn closest_stop<T>(stops: &[(f32, T)], t: f32) -> Option<usize> {
+    stops
+        .iter()
+        .enumerate()
+        .min_by(|(_, (a, _)), (_, (b, _))| (a - t).abs().total_cmp(&(b - t).abs()))
+        .map(|(i, _)| i)
+}
+
+fn even_positions(count: usize) -> impl Iterator<Item = f32> {
+    (0..count).map(move |i| i as f32 / (count - 1).max(1) as f32)
+}
+
+fn reversed_stops<T: Clone>(stops: &[(f32, T)]) -> Vec<(f32, T)> {
+    stops
+        .iter()
+        .rev()
+        .map(|(position, value)| (1.0 - position, value.clone()))
+        .collect()
+}
+
+// every stop position of both, sorted without duplicates
+fn merged_positions<A, B>(a: &[(f32, A)], b: &[(f32, B)]) -> impl Iterator<Item = f32> {
+    let mut positions: Vec<f32> = a
+        .iter()
+        .map(|(t, _)| *t)
+        .chain(b.iter().map(|(t, _)| *t))
+        .collect();
+    positions.sort_by(f32::total_cmp);
+    positions.dedup();
+    positions.into_iter()
+}
+
 impl Default for ScalarGradient {
     fn default() -> Self {
         Self::new(vec![(0.0, 0.0), (1.0, 1.0)])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn positions(gradient: &Gradient) -> Vec<f32> {
+        gradient.stops.iter().map(|(t, _)| *t).collect()
+    }
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        for i in 0..4 {
+            assert!((a[i] - b[i]).abs() < 0.01, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn insert_stop_keeps_order() {
+        let mut gradient = Gradient::new(vec![(0.0, RED), (1.0, BLUE)]);
+        assert_eq!(gradient.insert_stop(0.5, GREEN), 1);
+        assert_eq!(gradient.insert_stop(0.5, RED), 2);
+        assert_eq!(positions(&gradient), vec![0.0, 0.5, 0.5, 1.0]);
+        assert_eq!(gradient.stops[1].1, GradientColor::Srgb(GREEN));
+    }
+
+    #[test]
+    fn insert_sampled_stop_keeps_the_look() {
+        let mut gradient = Gradient::new(vec![(0.0, RED), (1.0, BLUE)]);
+        let before = gradient.sample_at(0.3);
+        gradient.insert_sampled_stop(0.5);
+        assert_eq!(gradient.stops.len(), 3);
+        assert_close(gradient.sample_at(0.3), before);
+    }
+
+    #[test]
+    fn remove_and_closest_stop() {
+        let mut gradient = Gradient::new(vec![(0.0, RED), (0.4, GREEN), (1.0, BLUE)]);
+        assert_eq!(gradient.closest_stop(0.3), Some(1));
+        assert_eq!(
+            gradient.remove_stop(1),
+            Some((0.4, GradientColor::Srgb(GREEN)))
+        );
+        assert_eq!(gradient.remove_stop(5), None);
+        assert_eq!(positions(&gradient), vec![0.0, 1.0]);
+        assert_eq!(
+            Gradient::new(Vec::<(f32, GradientColor)>::new()).closest_stop(0.5),
+            None
+        );
+    }
+
+    #[test]
+    fn move_stop_keeps_order() {
+        let mut gradient = Gradient::new(vec![(0.0, RED), (0.4, GREEN), (1.0, BLUE)]);
+        assert_eq!(gradient.move_stop(0, 0.7), Some(1));
+        assert_eq!(positions(&gradient), vec![0.4, 0.7, 1.0]);
+        assert_eq!(gradient.stops[1].1, GradientColor::Srgb(RED));
+        assert_eq!(gradient.move_stop(9, 0.5), None);
+    }
+
+    #[test]
+    fn resample_to_even_stops() {
+        let gradient = Gradient::new(vec![(0.0, RED), (0.2, GREEN), (1.0, BLUE)]);
+        let resampled = gradient.resample(5);
+        assert_eq!(positions(&resampled), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_close(resampled.sample_at(0.5), gradient.sample_at(0.5));
+        assert_eq!(resampled.stops[0].1, GradientColor::Srgb(RED));
+        assert_eq!(resampled.stops[4].1, GradientColor::Srgb(BLUE));
+    }
+
+    // one flat color at every time of day
+    fn flat_colors(color: [u8; 4]) -> SkyGradients {
+        SkyGradients {
+            sky_colors: vec![Gradient::new(vec![(0.0, color)])],
+            positions: vec![ScalarGradient::new(vec![(0.0, 0.5)])],
+        }
+    }
+
+    #[test]
+    fn sample_changed_skips_equal_colors() {
+        let colors = flat_colors(BLUE);
+        let mut bind_group = GradientBindGroup::default();
+        let sampled = colors.sample_changed(0.3, &bind_group).unwrap();
+        colors.sample_into(0.3, &mut bind_group);
+        assert_eq!(sampled, bind_group);
+        // a flat color is the same all day
+        assert!(colors.sample_changed(0.8, &bind_group).is_none());
+    }
+
+    #[test]
+    fn ground_bakes_into_lut() {
+        let (width, height) = (4, 65);
+        let sky = flat_colors(BLUE);
+        let ground = GroundGradients {
+            colors: flat_colors(GREEN),
+            horizon: GroundBindGroup::default(),
+        };
+        let mut lut = sky.bake_lut_colors(width, height);
+        ground.bake_into_lut(&mut lut, width, height);
+        let (blue, green) = ([0.0, 0.0, 1.0, 1.0], [0.0, 1.0, 0.0, 1.0]);
+        for x in 0..width as usize {
+            // top row straight up, bottom row straight down, the middle row is the horizon
+            assert_close(lut[x], blue);
+            assert_close(lut[(height as usize - 1) * width as usize + x], green);
+            let horizon = lut[(height as usize / 2) * width as usize + x];
+            assert_close(horizon, [0.0, 0.5, 0.5, 1.0]);
+        }
+    }
+
+    #[test]
+    fn hdr_stops_stay_linear() {
+        let hdr = GradientColor::Hdr(LinearRgba::new(4.0, 0.5, 0.0, 1.0));
+        assert_eq!(ColorSpace::Linear.encode(hdr), [4.0, 0.5, 0.0, 1.0]);
+        let oklab = ColorSpace::Oklab.encode(hdr);
+        let decoded = LinearRgba::from(Srgba::from_f32_array(ColorSpace::Oklab.decode(oklab)));
+        assert_close(decoded.to_f32_array(), [4.0, 0.5, 0.0, 1.0]);
+
+        let gradient =
+            Gradient::new(vec![(0.0, hdr), (1.0, hdr)]).with_color_space(ColorSpace::Linear);
+        let sampled = LinearRgba::from(Srgba::from_f32_array(gradient.sample_at(0.5)));
+        assert_close(sampled.to_f32_array(), [4.0, 0.5, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn resample_keeps_hdr() {
+        let gradient = Gradient::new(vec![(0.0, LinearRgba::new(4.0, 2.0, 1.0, 1.0))]);
+        let resampled = gradient.resample(2);
+        assert!(resampled.is_hdr());
+        assert_close(resampled.sample_at(1.0), gradient.sample_at(1.0));
+    }
+
+    #[test]
+    fn reversed_flips_positions() {
+        let gradient = Gradient::new(vec![(0.0, RED), (0.25, GREEN), (1.0, BLUE)]);
+        let reversed = gradient.reversed();
+        assert_eq!(positions(&reversed), vec![0.0, 0.75, 1.0]);
+        assert_close(reversed.sample_at(0.1), gradient.sample_at(0.9));
+        assert_eq!(reversed.reversed(), gradient);
+    }
+
+    #[test]
+    fn blend_two_gradients() {
+        let a = Gradient::new(vec![(0.0, RED), (1.0, RED)]);
+        let b = Gradient::new(vec![(0.0, BLUE), (0.5, GREEN), (1.0, BLUE)])
+            .with_interpolation(Interpolation::SmoothStep);
+        assert_eq!(positions(&a.blend(&b, 0.3)), vec![0.0, 0.5, 1.0]);
+        assert_close(a.blend(&b, 0.0).sample_at(0.5), a.sample_at(0.5));
+        assert_close(a.blend(&b, 1.0).sample_at(0.5), b.sample_at(0.5));
+        assert_close(a.blend(&b, 0.5).sample_at(0.0), [0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(a.blend(&b, 0.7).interpolation, Interpolation::SmoothStep);
+    }
+
+    #[test]
+    fn gradient_is_a_curve() {
+        let gradient = Gradient::new(vec![(0.0, RED), (1.0, BLUE)]);
+        assert_eq!(gradient.domain(), Interval::UNIT);
+        assert_eq!(gradient.sample(2.0), None);
+        assert_eq!(gradient.sample_clamped(2.0), Srgba::BLUE);
+        // runs the gradient over 10 seconds, backwards
+        let curve = gradient
+            .reparametrize_linear(Interval::new(0.0, 10.0).unwrap())
+            .unwrap()
+            .reverse()
+            .unwrap();
+        assert_eq!(curve.sample(0.0), Some(Srgba::BLUE));
+        assert_eq!(curve.sample(10.0), Some(Srgba::RED));
+    }
+
+    #[test]
+    fn scalar_gradient_toolkit() {
+        let mut gradient = ScalarGradient::new(vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(gradient.insert_stop(0.5, 2.0), 1);
+        assert_eq!(gradient.closest_stop(0.6), Some(1));
+        assert_eq!(gradient.move_stop(1, 0.25), Some(1));
+        assert_eq!(gradient.stops, vec![(0.0, 0.0), (0.25, 2.0), (1.0, 1.0)]);
+        assert_eq!(gradient.remove_stop(1), Some((0.25, 2.0)));
+
+        gradient.insert_sampled_stop(0.5);
+        assert_eq!(gradient.stops[1], (0.5, 0.5));
+        assert_eq!(
+            gradient.resample(3).stops,
+            vec![(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)]
+        );
+        assert_eq!(gradient.reversed().sample_at(0.25), 0.75);
+
+        let other = ScalarGradient::new(vec![(0.0, 1.0), (0.2, 1.0)]);
+        let blended = gradient.blend(&other, 0.5);
+        assert_eq!(blended.stops.len(), 4);
+        assert_eq!(blended.sample_at(0.0), 0.5);
+        assert_eq!(blended.sample_at(1.0), 1.0);
+    }
+
+    #[test]
+    fn scalar_interpolation_modes() {
+        let stops = vec![(0.0, 0.0), (0.5, 1.0), (1.0, 3.0)];
+        let sample = |interpolation: Interpolation, t: f32| {
+            ScalarGradient::new(stops.clone())
+                .with_interpolation(interpolation)
+                .sample_at(t)
+        };
+        // (interpolation, t, value)
+        let cases = [
+            // holds the left value until the next stop
+            (Interpolation::Step, 0.25, 0.0),
+            (Interpolation::Step, 0.49, 0.0),
+            (Interpolation::Step, 0.5, 1.0),
+            (Interpolation::Step, 0.75, 1.0),
+            (Interpolation::Linear, 0.25, 0.5),
+            (Interpolation::Linear, 0.75, 2.0),
+            // eased, 0.25 of the way is 0.15625 of the value
+            (Interpolation::SmoothStep, 0.125, 0.15625),
+            (Interpolation::SmoothStep, 0.25, 0.5),
+            // passes through the stops, but curves between them
+            (Interpolation::CatmullRom, 0.0, 0.0),
+            (Interpolation::CatmullRom, 0.5, 1.0),
+            (Interpolation::CatmullRom, 1.0, 3.0),
+            (Interpolation::CatmullRom, 0.25, 0.375),
+        ];
+        for (interpolation, t, expected) in cases {
+            let value = sample(interpolation, t);
+            assert!(
+                (value - expected).abs() < 1e-4,
+                "{interpolation:?} at {t}: {value} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn color_interpolation_modes() {
+        let sample = |interpolation: Interpolation, t: f32| {
+            Gradient::new(vec![(0.0, RED), (0.5, GREEN), (1.0, BLUE)])
+                .with_interpolation(interpolation)
+                .sample_at(t)
+        };
+        assert_close(sample(Interpolation::Step, 0.49), [1.0, 0.0, 0.0, 1.0]);
+        assert_close(sample(Interpolation::Step, 0.5), [0.0, 1.0, 0.0, 1.0]);
+        assert_close(sample(Interpolation::Step, 0.99), [0.0, 1.0, 0.0, 1.0]);
+        assert_close(sample(Interpolation::Linear, 0.25), [0.5, 0.5, 0.0, 1.0]);
+        assert_close(
+            sample(Interpolation::SmoothStep, 0.125),
+            [0.84375, 0.15625, 0.0, 1.0],
+        );
+        for (t, color) in [(0.0, RED), (0.5, GREEN), (1.0, BLUE)] {
+            let stop = Srgba::from_u8_array(color).to_f32_array();
+            assert_close(sample(Interpolation::CatmullRom, t), stop);
+        }
+        // overshooting u8 stops are clamped, not turned into HDR colors
+        let curved = sample(Interpolation::CatmullRom, 0.3);
+        assert!(curved.iter().all(|channel| (0.0..=1.0).contains(channel)));
+    }
+
+    #[test]
+    fn color_space_midpoints() {
+        const ORANGE: [u8; 4] = [255, 128, 0, 255];
+        let midpoint = |color_space: ColorSpace| {
+            Gradient::new(vec![(0.0, ORANGE), (1.0, BLUE)])
+                .with_color_space(color_space)
+                .sample_at(0.5)
+        };
+        let srgb = midpoint(ColorSpace::Srgb);
+        assert_close(srgb, [0.5, 0.25, 0.5, 1.0]);
+
+        // half the light of each, brighter than the sRGB lerp
+        let linear = midpoint(ColorSpace::Linear);
+        let expected = Srgba::from(
+            LinearRgba::from(Srgba::from_u8_array(ORANGE))
+                .mix(&LinearRgba::from(Srgba::from_u8_array(BLUE)), 0.5),
+        );
+        assert_close(linear, expected.to_f32_array());
+        assert!(linear[0] > srgb[0] + 0.1);
+
+        let oklab = midpoint(ColorSpace::Oklab);
+        let expected = Srgba::from(
+            Oklaba::from(Srgba::from_u8_array(ORANGE))
+                .mix(&Oklaba::from(Srgba::from_u8_array(BLUE)), 0.5),
+        );
+        assert_close(oklab, expected.to_f32_array());
+        // the muddy sRGB midpoint is darker than halfway between the two colors
+        let lightness = |color: [f32; 4]| Oklaba::from(Srgba::from_f32_array(color)).lightness;
+        assert!(lightness(oklab) > lightness(srgb) + 0.02);
+        assert!(oklab.iter().zip(srgb).any(|(a, b)| (a - b).abs() > 0.05));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn interpolation_and_color_space_round_trip() {
+        let gradient = Gradient::new(vec![(0.0, RED), (1.0, BLUE)])
+            .with_interpolation(Interpolation::CatmullRom)
+            .with_color_space(ColorSpace::Oklab);
+        let ron_text = ron::to_string(&gradient).unwrap();
+        assert_eq!(ron::from_str::<Gradient>(&ron_text).unwrap(), gradient);
+
+        let scalar = ScalarGradient::default().with_interpolation(Interpolation::Step);
+        let ron_text = ron::to_string(&scalar).unwrap();
+        assert_eq!(ron::from_str::<ScalarGradient>(&ron_text).unwrap(), scalar);
+
+        // files from before the new fields
+        let old: Gradient = ron::from_str("(stops: [])").unwrap();
+        assert_eq!(old.interpolation, Interpolation::Linear);
+        assert_eq!(old.color_space, ColorSpace::Srgb);
+        let old: ScalarGradient = ron::from_str("(stops: [(0.0, 1.0)])").unwrap();
+        assert_eq!(old.interpolation, Interpolation::Linear);
+    }
+
+    #[test]
+    fn scalar_gradient_is_a_curve() {
+        let gradient = ScalarGradient::new(vec![(0.0, 0.0), (1.0, 2.0)]);
+        let doubled = gradient.map(|value| value * 2.0);
+        assert_eq!(doubled.sample(0.5), Some(2.0));
+        assert_eq!(doubled.sample(-0.5), None);
+    }
+}
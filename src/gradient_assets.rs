@@ -0,0 +1,243 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, RenderAssetUsages, io::Reader},
+    image::{CompressedImageFormats, ImageSampler, ImageType, TextureError},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    ambient_driver::AmbientColorsBuilder,
+    gradient::{Gradient, GroundGradientBuilder, SkyGradientBuilder},
+    gradient_import::{self, GradientImportError},
+};
+
+/// loads gradients and color builders from RON files:
+/// `*.gradient.ron`, `*.sky_gradient.ron`, `*.ground_gradient.ron` and `*.ambient.ron`.
+/// gradients are also imported from GIMP `*.ggr`, CSS `*.css` and `*.gradient.png` image strips,
+/// a whole sky from a `*.sky_gradient.png`, see [`gradient_import`].
+/// enable bevy's `file_watcher` feature to pick up edits while the game runs
+pub struct GradientAssetsPlugin;
+
+impl Plugin for GradientAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Gradient>()
+            .register_asset_loader(RonAssetLoader::<Gradient>::new(&["gradient.ron"]));
+        app.register_asset_loader(TextGradientLoader {
+            extensions: &["ggr"],
+            import: gradient_import::gradient_from_ggr,
+        })
+        .register_asset_loader(TextGradientLoader {
+            extensions: &["css"],
+            import: gradient_import::gradient_from_css,
+        })
+        .register_asset_loader(ImageGradientLoader);
+        app.init_asset::<SkyGradientBuilder>()
+            .register_asset_loader(RonAssetLoader::<SkyGradientBuilder>::new(&[
+                "sky_gradient.ron",
+            ]))
+            .register_asset_loader(ImageSkyGradientLoader);
+        app.init_asset::<GroundGradientBuilder>()
+            .register_asset_loader(RonAssetLoader::<GroundGradientBuilder>::new(&[
+                "ground_gradient.ron",
+            ]));
+        app.init_asset::<AmbientColorsBuilder>()
+            .register_asset_loader(RonAssetLoader::<AmbientColorsBuilder>::new(&[
+                "ambient.ron",
+            ]));
+
+        app.add_systems(
+            Update,
+            (
+                apply_resource_asset::<SkyGradientBuilder>,
+                apply_resource_asset::<GroundGradientBuilder>,
+                apply_resource_asset::<AmbientColorsBuilder>,
+            ),
+        );
+    }
+}
+
+/// insert to drive a resource from an asset file, the resource is overwritten
+/// when the asset loads and every time the file changes.
+/// the resource must already exist, inserted by its driver plugin
+/// ```ignore
+/// commands.insert_resource(SkyResourceHandle::<SkyGradientBuilder>(
+///     asset_server.load("gradients/sky.sky_gradient.ron"),
+/// ));
+/// ```
+#[derive(Resource)]
+pub struct SkyResourceHandle<A: Asset>(pub Handle<A>);
+
+fn apply_resource_asset<A: Asset + Resource + Clone>(
+    mut events: MessageReader<AssetEvent<A>>,
+    handle: Option<Res<SkyResourceHandle<A>>>,
+    assets: Res<Assets<A>>,
+    resource: Option<ResMut<A>>,
+) {
+    let (Some(handle), Some(mut resource)) = (handle, resource) else {
+        events.clear();
+        return;
+    };
+    // a new handle was inserted, the asset may have loaded before that
+    let mut apply = handle.is_changed();
+    for event in events.read() {
+        apply |= event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0);
+    }
+    if apply && let Some(asset) = assets.get(&handle.0) {
+        *resource = asset.clone();
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RonAssetLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// loads any deserializable asset from a RON file
+pub struct RonAssetLoader<A> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> A>,
+}
+
+impl<A> RonAssetLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonAssetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<A, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GradientImportLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Texture(#[from] TextureError),
+    #[error(transparent)]
+    Import(#[from] GradientImportError),
+}
+
+/// loads a [`Gradient`] from a text format, like GIMP `.ggr` or CSS
+pub struct TextGradientLoader {
+    pub extensions: &'static [&'static str],
+    pub import: fn(&str) -> Result<Gradient, GradientImportError>,
+}
+
+impl AssetLoader for TextGradientLoader {
+    type Asset = Gradient;
+    type Settings = ();
+    type Error = GradientImportLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Gradient, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok((self.import)(&String::from_utf8(bytes)?)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+/// which row of pixels becomes the gradient
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ImageGradientLoaderSettings {
+    /// 0 = the top row
+    pub row: u32,
+}
+
+/// loads a [`Gradient`] from a row of pixels of a `*.gradient.png`
+pub struct ImageGradientLoader;
+
+impl AssetLoader for ImageGradientLoader {
+    type Asset = Gradient;
+    type Settings = ImageGradientLoaderSettings;
+    type Error = GradientImportLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Gradient, Self::Error> {
+        let image = read_png(reader).await?;
+        Ok(gradient_import::gradient_from_image_row(
+            &image,
+            settings.row,
+        )?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gradient.png"]
+    }
+}
+
+/// loads a [`SkyGradientBuilder`] from a `*.sky_gradient.png`,
+/// see [`gradient_import::sky_gradient_builder_from_image`]
+pub struct ImageSkyGradientLoader;
+
+impl AssetLoader for ImageSkyGradientLoader {
+    type Asset = SkyGradientBuilder;
+    type Settings = ();
+    type Error = GradientImportLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<SkyGradientBuilder, Self::Error> {
+        let image = read_png(reader).await?;
+        Ok(gradient_import::sky_gradient_builder_from_image(&image)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sky_gradient.png"]
+    }
+}
+
+async fn read_png(reader: &mut dyn Reader) -> Result<Image, GradientImportLoaderError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    Ok(Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::MAIN_WORLD,
+    )?)
+}
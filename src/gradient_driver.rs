@@ -1,25 +1,80 @@
 use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    mesh::MeshTag,
     prelude::*,
-    render::render_resource::Extent3d,
-    window::{PrimaryWindow, WindowResized},
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
 };
 
 use crate::{
-    cycle::{SkyTime, SkyTimeSettings},
-    gradient::{Gradient, SkyGradientBuilder, SkyGradients},
+    cycle::{SkyTime, SkyTimeSettings, SkyTimeWarp},
+    gradient::{GroundGradientBuilder, GroundGradients, SkyGradientBuilder, SkyGradients},
     gradient_material::FullGradientMaterial,
-    plugin::GradientTextureHandle,
+    instance::SkyInstanceOf,
+    plugin::{
+        DefaultSkyFilter, GradientCameraTag, GradientTextureHandle, PrimaryMagnetFilter,
+        SkySettings,
+    },
+    sky_material::FullSkyMaterial,
+    sun::SunPath,
+    utils,
 };
 
 /// animates the sky gradients, REQUIRES CyclePlugin.
 #[derive(Clone, Default)]
 pub struct GradientDriverPlugin {
     pub sky_colors_builder: SkyGradientBuilder,
+    pub mode: GradientDriverMode,
+    /// opt in, separate colors below the horizon.
+    /// baked into the lut with [`GradientDriverMode::BakedLut`]
+    pub ground_colors_builder: Option<GroundGradientBuilder>,
+}
+
+/// how the day cycle colors reach the gradient material
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub enum GradientDriverMode {
+    /// sample the gradients every frame, and write the colors into the material
+    #[default]
+    Sampled,
+    /// bake the whole day into a lookup texture, only rebuilt when the colors or timings change.
+    /// every frame only the cycle percent is sent, through the MeshTag of the gradient mesh.
+    /// SkyInstance cameras are always sampled
+    BakedLut {
+        /// texels along the cycle, the x axis
+        cycle_resolution: u32,
+        /// texels from straight down to straight up, the y axis
+        height_resolution: u32,
+    },
+}
+
+impl GradientDriverMode {
+    pub fn baked_lut() -> Self {
+        Self::BakedLut {
+            cycle_resolution: 256,
+            height_resolution: 128,
+        }
+    }
+
+    pub fn is_baked(&self) -> bool {
+        matches!(self, Self::BakedLut { .. })
+    }
 }
 
 impl Plugin for GradientDriverPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, drive_gradients);
+        app.insert_resource(self.mode.clone());
+        app.add_systems(
+            Update,
+            (
+                drive_gradients.run_if(|mode: Res<GradientDriverMode>| !mode.is_baked()),
+                (
+                    bake_gradient_lut,
+                    drive_gradient_lut_time.run_if(|mode: Res<GradientDriverMode>| mode.is_baked()),
+                )
+                    .chain()
+                    .after(update_sky_colors_builder),
+            ),
+        );
 
         // initial sky color values will be wrong, until SkyTimeSettings can be fetched in update_sky_colors_builer
         app.insert_resource(self.sky_colors_builder.build(&SkyTimeSettings::default()));
@@ -29,14 +84,31 @@ impl Plugin for GradientDriverPlugin {
                 resource_changed::<SkyTimeSettings>.or(resource_changed::<SkyGradientBuilder>),
             ),
         );
-        app.add_systems(PostUpdate, resize_gradient_on_window_change);
+        app.add_systems(PostUpdate, resize_gradient_to_camera);
         // save the color builder so we can rebuild SkyColors on SkyTimeSettings changes
         app.insert_resource(self.sky_colors_builder.clone());
+
+        if let Some(ground_colors_builder) = &self.ground_colors_builder {
+            app.insert_resource(ground_colors_builder.build(&SkyTimeSettings::default()));
+            app.insert_resource(ground_colors_builder.clone());
+            app.add_systems(
+                Update,
+                (
+                    update_ground_colors_builder.run_if(
+                        resource_changed::<SkyTimeSettings>
+                            .or(resource_changed::<GroundGradientBuilder>),
+                    ),
+                    drive_ground_gradients.run_if(|mode: Res<GradientDriverMode>| !mode.is_baked()),
+                )
+                    .chain()
+                    .before(bake_gradient_lut),
+            );
+        }
     }
 }
 
 // color stops change
-fn update_sky_colors_builder(
+pub(crate) fn update_sky_colors_builder(
     sky_time_settings: Res<SkyTimeSettings>,
     mut sky_colors: ResMut<SkyGradients>,
     sky_colors_builder: Res<SkyGradientBuilder>,
@@ -44,69 +116,237 @@ fn update_sky_colors_builder(
     *sky_colors = sky_colors_builder.build(&sky_time_settings);
 }
 
+// ground color stops change
+pub(crate) fn update_ground_colors_builder(
+    sky_time_settings: Res<SkyTimeSettings>,
+    mut ground_colors: ResMut<GroundGradients>,
+    ground_colors_builder: Res<GroundGradientBuilder>,
+) {
+    *ground_colors = ground_colors_builder.build(&sky_time_settings);
+}
+
+fn drive_ground_gradients(
+    sky_time_settings: Res<SkyTimeSettings>,
+    sky_time: Res<SkyTime>,
+    sky_time_warp: Res<SkyTimeWarp>,
+    sun_path: Option<Res<SunPath>>,
+    ground_colors: Res<GroundGradients>,
+    gradient_meshes: Query<&MeshMaterial3d<FullGradientMaterial>, DefaultSkyFilter>,
+    mut gradient_materials: ResMut<Assets<FullGradientMaterial>>,
+) {
+    let Ok(material_handle) = gradient_meshes.single() else {
+        return;
+    };
+    let Some(gradient_material) = gradient_materials.get(material_handle) else {
+        return;
+    };
+
+    let time = sky_time_warp.warp_time(&sky_time_settings, sky_time.time);
+    let time = sun_path.map_or(time, |sun_path| {
+        sun_path.gradient_time(&sky_time_settings, time)
+    });
+    let percent = sky_time_settings.time_percent(time);
+    // only touch the material when the colors changed, get_mut marks it as modified
+    let sampled = ground_colors
+        .colors
+        .sample_changed(percent, &gradient_material.ground_gradient_bind_group);
+    if sampled.is_none()
+        && gradient_material.ground == ground_colors.horizon
+        && gradient_material.feature_ground_enabled == 1
+    {
+        return;
+    }
+    let Some(gradient_material) = gradient_materials.get_mut(material_handle) else {
+        return;
+    };
+    if let Some(sampled) = sampled {
+        gradient_material.ground_gradient_bind_group = sampled;
+    }
+    gradient_material.ground.clone_from(&ground_colors.horizon);
+    gradient_material.feature_ground_enabled = 1;
+}
+
 /// drive the sky materials
 fn drive_gradients(
     sky_time_settings: Res<SkyTimeSettings>,
     sky_time: Res<SkyTime>,
+    sky_time_warp: Res<SkyTimeWarp>,
+    sun_path: Option<Res<SunPath>>,
     sky_colors: Res<SkyGradients>,
-    skyboxes: Query<&mut MeshMaterial3d<FullGradientMaterial>>,
+    skyboxes: Query<&mut MeshMaterial3d<FullGradientMaterial>, DefaultSkyFilter>,
     mut sky_materials: ResMut<Assets<FullGradientMaterial>>,
 ) {
     let skybox_material_handle = skyboxes
         .single()
         .expect("1 entity with SkyGradientMaterial");
     let skybox_material = sky_materials
-        .get_mut(skybox_material_handle)
+        .get(skybox_material_handle)
         .expect("SkyBoxMaterial");
 
-    let percent = sky_time_settings.time_percent(sky_time.time);
-
-    let color_from_gradient = |gradient: &Gradient| -> [f32; 4] { gradient.sample_at(percent) };
-    skybox_material.gradient_bind_group.color_stops[0] =
-        color_from_gradient(&sky_colors.sky_color0).into();
-    skybox_material.gradient_bind_group.color_stops[1] =
-        color_from_gradient(&sky_colors.sky_color1).into();
-    skybox_material.gradient_bind_group.color_stops[2] =
-        color_from_gradient(&sky_colors.sky_color2).into();
-    skybox_material.gradient_bind_group.color_stops[3] =
-        color_from_gradient(&sky_colors.sky_color3).into();
+    let time = sky_time_warp.warp_time(&sky_time_settings, sky_time.time);
+    let time = sun_path.map_or(time, |sun_path| {
+        sun_path.gradient_time(&sky_time_settings, time)
+    });
+    let percent = sky_time_settings.time_percent(time);
+    // a paused or static sky leaves the material, and the stop buffers, untouched
+    let Some(sampled) = sky_colors.sample_changed(percent, &skybox_material.gradient_bind_group)
+    else {
+        return;
+    };
+    if let Some(skybox_material) = sky_materials.get_mut(skybox_material_handle) {
+        skybox_material.gradient_bind_group = sampled;
+    }
 }
 
-fn resize_gradient_on_window_change(
-    mut resize_events: MessageReader<WindowResized>,
+// (re)bake the lut, or turn it off when going back to sampling.
+// retried every frame until the gradient material exists, and redone for a new material
+#[allow(clippy::too_many_arguments)]
+fn bake_gradient_lut(
+    mode: Res<GradientDriverMode>,
+    sky_colors: Res<SkyGradients>,
+    ground_colors: Option<Res<GroundGradients>>,
+    sky_settings: Res<SkySettings>,
+    gradient_meshes: Query<&MeshMaterial3d<FullGradientMaterial>, DefaultSkyFilter>,
+    mut gradient_materials: ResMut<Assets<FullGradientMaterial>>,
     mut images: ResMut<Assets<Image>>,
-    aurora_handles: Res<GradientTextureHandle>,
-    primary_windows: Query<&Window, With<PrimaryWindow>>,
-    mut repeated_calls: Local<i32>,
+    mut baked_material: Local<Option<AssetId<FullGradientMaterial>>>,
 ) {
-    let mut update_texture = false;
-    for event in resize_events.read() {
-        let is_primary = primary_windows.get(event.window).is_ok();
-        update_texture |= is_primary;
+    let ground_changed = ground_colors
+        .as_ref()
+        .is_some_and(|ground| ground.is_changed());
+    if mode.is_changed() || sky_colors.is_changed() || ground_changed {
+        *baked_material = None;
     }
-    if !update_texture {
-        *repeated_calls = 0;
+    let Ok(material_handle) = gradient_meshes.single() else {
+        return;
+    };
+    if *baked_material == Some(material_handle.id()) {
         return;
     }
+    let Some(gradient_material) = gradient_materials.get_mut(material_handle) else {
+        return;
+    };
+    *baked_material = Some(material_handle.id());
 
-    *repeated_calls += 1;
-    if *repeated_calls > 10 {
-        warn!(
-            "aurora texture, was resized every last:{} frames!",
-            *repeated_calls
-        );
-        warn!("make sure AuroraSettings doesn't mutate every frame");
+    let GradientDriverMode::BakedLut {
+        cycle_resolution,
+        height_resolution,
+    } = *mode
+    else {
+        gradient_material.feature_lut_enabled = 0;
+        return;
+    };
+
+    let size = Extent3d {
+        width: cycle_resolution.max(2),
+        height: height_resolution.max(2),
+        depth_or_array_layers: 1,
+    };
+    let mut colors = sky_colors.bake_lut_colors(size.width, size.height);
+    if let Some(ground_colors) = &ground_colors {
+        ground_colors.bake_into_lut(&mut colors, size.width, size.height);
+        // the ground is in the lut, the shader shouldn't blend it in again
+        gradient_material.feature_ground_enabled = 0;
+    }
+    let baked = bake_lut_image(colors, size, sky_settings.hdr);
+    let lut_image = gradient_material
+        .lut_image
+        .as_ref()
+        .and_then(|handle| images.get_mut(handle));
+    match lut_image {
+        Some(lut_image) if lut_image.texture_descriptor == baked.texture_descriptor => {
+            lut_image.data = baked.data;
+        }
+        _ => {
+            gradient_material.lut_image = Some(images.add(baked));
+        }
     }
+    gradient_material.feature_lut_enabled = 1;
+}
 
-    let Ok(window) = primary_windows.single() else {
-        return;
+// HDR colors above 1.0 need a float texture
+fn bake_lut_image(colors: Vec<[f32; 4]>, size: Extent3d, hdr: bool) -> Image {
+    let mut lut_image = if hdr {
+        let mut lut_image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0; 8],
+            TextureFormat::Rgba16Float,
+            RenderAssetUsages::default(),
+        );
+        for (i, color) in colors.into_iter().enumerate() {
+            let (x, y) = (i as u32 % size.width, i as u32 / size.width);
+            // written as is, the lut holds the same values as the gradient stops
+            let color = Color::LinearRgba(LinearRgba::from_f32_array(color));
+            if let Err(err) = lut_image.set_color_at(x, y, color) {
+                warn!("SkyPlugin: failed to bake gradient lut: {err}");
+                break;
+            }
+        }
+        lut_image
+    } else {
+        Image::new(
+            size,
+            TextureDimension::D2,
+            colors
+                .into_iter()
+                .flatten()
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect(),
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::default(),
+        )
     };
+    lut_image.sampler = ImageSampler::linear();
+    lut_image
+}
+
+// the default gradient mesh and skybox
+type LutTimeFilter = (
+    Or<(
+        With<MeshMaterial3d<FullGradientMaterial>>,
+        With<MeshMaterial3d<FullSkyMaterial>>,
+    )>,
+    Without<SkyInstanceOf>,
+);
 
-    if let Some(image) = images.get_mut(&aurora_handles.render_target) {
-        image.resize(Extent3d {
-            width: (window.width() as u32).max(2),
-            height: (window.height() as u32).max(2),
-            depth_or_array_layers: 1,
-        });
+// the only per frame work of the lut: where in the day we are.
+// the skybox needs it too, when it inlines the gradient
+fn drive_gradient_lut_time(
+    sky_time_settings: Res<SkyTimeSettings>,
+    sky_time: Res<SkyTime>,
+    sky_time_warp: Res<SkyTimeWarp>,
+    sun_path: Option<Res<SunPath>>,
+    mut gradient_meshes: Query<&mut MeshTag, LutTimeFilter>,
+) {
+    let time = sky_time_warp.warp_time(&sky_time_settings, sky_time.time);
+    let time = sun_path.map_or(time, |sun_path| {
+        sun_path.gradient_time(&sky_time_settings, time)
+    });
+    let tag = MeshTag(sky_time_settings.time_percent(time).to_bits());
+    for mut mesh_tag in &mut gradient_meshes {
+        mesh_tag.set_if_neq(tag.clone());
     }
 }
+
+// the gradient texture matches what the primary SkyboxMagnetTag camera renders:
+// its viewport, or its whole window or image.
+// an inlined gradient has no gradient camera, its texture stays 2x2
+fn resize_gradient_to_camera(
+    mut images: ResMut<Assets<Image>>,
+    gradient_handles: Res<GradientTextureHandle>,
+    magnet_cameras: Query<&Camera, PrimaryMagnetFilter>,
+    gradient_cameras: Query<(), With<GradientCameraTag>>,
+) {
+    if gradient_cameras.is_empty() {
+        return;
+    }
+    let Some(size) = magnet_cameras
+        .iter()
+        .next()
+        .and_then(Camera::physical_viewport_size)
+    else {
+        return;
+    };
+    utils::resize_render_target(&mut images, &gradient_handles.render_target, size);
+}
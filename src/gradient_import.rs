@@ -0,0 +1,620 @@
+use std::f32::consts::PI;
+
+use bevy::{color::palettes::css, image::TextureAccessError, prelude::*};
+
+use crate::{
+    gradient::{Gradient, GradientBuilder, GradientColor, SkyGradientBuilder, SkyGradientStop},
+    utils,
+};
+
+/// extra stops per GIMP segment, for blend functions that aren't linear
+const GGR_SEGMENT_SAMPLES: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GradientImportError {
+    #[error("not a GIMP gradient, the first line must be \"GIMP Gradient\"")]
+    NotGgr,
+    #[error("GIMP gradient line {line}: {reason}")]
+    Ggr { line: usize, reason: String },
+    #[error("no linear-gradient(...) found")]
+    NotLinearGradient,
+    #[error("unclosed parenthesis in {0:?}")]
+    UnclosedParenthesis(String),
+    #[error("unknown css color {0:?}")]
+    CssColor(String),
+    #[error("unsupported css stop position {0:?}, use a percentage")]
+    CssPosition(String),
+    #[error("a gradient needs at least one color")]
+    NoColors,
+    #[error(transparent)]
+    Image(#[from] TextureAccessError),
+    #[error(
+        "the sky gradient image must be {expected} pixels high, one row per time of day, got {got}"
+    )]
+    SkyImageHeight { expected: u32, got: u32 },
+}
+
+/// gradient from a GIMP `.ggr` file.
+/// HSV segments are blended the short way around the hue circle
+pub fn gradient_from_ggr(text: &str) -> Result<Gradient, GradientImportError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    if lines.next().map(|(_, line)| line) != Some("GIMP Gradient") {
+        return Err(GradientImportError::NotGgr);
+    }
+    let ggr_error = |line: usize, reason: &str| GradientImportError::Ggr {
+        line,
+        reason: reason.to_string(),
+    };
+
+    // the name line is optional in older files
+    let (count_line, count) = match lines.next() {
+        Some((_, line)) if line.starts_with("Name:") => lines.next(),
+        other => other,
+    }
+    .ok_or(GradientImportError::NoColors)?;
+    let count: usize = count
+        .parse()
+        .map_err(|_| ggr_error(count_line, "expected the number of segments"))?;
+
+    let mut stops = Vec::new();
+    for _ in 0..count {
+        let (line, segment) = lines
+            .next()
+            .ok_or_else(|| ggr_error(count_line, "fewer segments than announced"))?;
+        let values = segment
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ggr_error(line, "segment values must be numbers"))?;
+        if values.len() < 13 {
+            return Err(ggr_error(line, "a segment has at least 13 values"));
+        }
+        let (left, middle, right) = (values[0], values[1], values[2]);
+        let left_color = Srgba::new(values[3], values[4], values[5], values[6]);
+        let right_color = Srgba::new(values[7], values[8], values[9], values[10]);
+        let blend = values[11] as u32;
+        let hsv = values[12] as u32 != 0;
+
+        let width = (right - left).max(f32::EPSILON);
+        let middle = ((middle - left) / width).clamp(0.0001, 0.9999);
+        let color_at = |pos: f32| {
+            let factor = ggr_blend_factor(blend, middle, pos);
+            let color = if hsv {
+                Srgba::from(Hsva::from(left_color).mix(&Hsva::from(right_color), factor))
+            } else {
+                left_color.mix(&right_color, factor)
+            };
+            GradientColor::Srgb(color.to_u8_array())
+        };
+
+        match blend {
+            // linear rgb is exact with a stop at the midpoint
+            0 if !hsv => {
+                stops.push((left, color_at(0.0)));
+                stops.push((left + middle * width, color_at(middle)));
+                stops.push((right, color_at(1.0)));
+            }
+            // step, a hard edge at the midpoint
+            5 => {
+                let edge = left + middle * width;
+                stops.push((left, color_at(0.0)));
+                stops.push((edge, color_at(0.0)));
+                stops.push((edge, color_at(1.0)));
+                stops.push((right, color_at(1.0)));
+            }
+            _ => {
+                for i in 0..=GGR_SEGMENT_SAMPLES {
+                    let pos = i as f32 / GGR_SEGMENT_SAMPLES as f32;
+                    stops.push((left + pos * width, color_at(pos)));
+                }
+            }
+        }
+    }
+    if stops.is_empty() {
+        return Err(GradientImportError::NoColors);
+    }
+    Ok(Gradient::new(stops))
+}
+
+// how far from the left to the right color, pos: 0.0 -> 1.0 inside the segment
+fn ggr_blend_factor(blend: u32, middle: f32, pos: f32) -> f32 {
+    let linear = if pos <= middle {
+        0.5 * pos / middle
+    } else {
+        0.5 + 0.5 * (pos - middle) / (1.0 - middle)
+    };
+    match blend {
+        // curved
+        1 => pos.powf(0.5f32.ln() / middle.ln()),
+        // sine
+        2 => ((-PI / 2.0 + PI * linear).sin() + 1.0) * 0.5,
+        // sphere increasing
+        3 => (1.0 - (linear - 1.0) * (linear - 1.0)).sqrt(),
+        // sphere decreasing
+        4 => 1.0 - (1.0 - linear * linear).sqrt(),
+        5 => (pos >= middle) as u32 as f32,
+        _ => linear,
+    }
+}
+
+/// gradient from a CSS `linear-gradient(...)`, like the ones copied from Figma.
+/// `background: ` in front of it is fine. the direction is ignored,
+/// positions run along the gradient line: the first color is 0.0, the last 1.0
+pub fn gradient_from_css(text: &str) -> Result<Gradient, GradientImportError> {
+    let start = text
+        .find("linear-gradient(")
+        .ok_or(GradientImportError::NotLinearGradient)?
+        + "linear-gradient(".len();
+    let args = &text[start..];
+    let end = closing_parenthesis(args)
+        .ok_or_else(|| GradientImportError::UnclosedParenthesis(text.to_string()))?;
+    let args = split_top_level(&args[..end], ',');
+
+    let mut colors: Vec<(GradientColor, Option<f32>)> = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let arg = arg.trim();
+        if i == 0 && is_css_direction(arg) {
+            continue;
+        }
+        let parts = split_top_level(arg, ' ');
+        let mut parts = parts
+            .iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty());
+        let Some(first) = parts.next() else {
+            continue;
+        };
+        // a lone percentage is a color hint, the midpoint is kept at the middle
+        if first.ends_with('%') && !first.contains('(') {
+            continue;
+        }
+        let color = css_color(first)?;
+        let positions = parts.map(css_position).collect::<Result<Vec<_>, _>>()?;
+        if positions.is_empty() {
+            colors.push((color, None));
+        }
+        // "red 10% 20%" is a solid band
+        for position in positions {
+            colors.push((color, Some(position)));
+        }
+    }
+    if colors.is_empty() {
+        return Err(GradientImportError::NoColors);
+    }
+    Ok(Gradient::new(css_fill_positions(colors)))
+}
+
+// positions left out are spread evenly between the known ones, and never go backwards
+fn css_fill_positions(colors: Vec<(GradientColor, Option<f32>)>) -> Vec<(f32, GradientColor)> {
+    let last = colors.len() - 1;
+    let mut positions: Vec<Option<f32>> = colors.iter().map(|(_, position)| *position).collect();
+    positions[0] = positions[0].or(Some(0.0));
+    positions[last] = positions[last].or(Some(1.0));
+
+    let mut known = 0;
+    for i in 1..=last {
+        let Some(position) = positions[i] else {
+            continue;
+        };
+        let from = positions[known].unwrap();
+        for (step, missing) in positions[known + 1..i].iter_mut().enumerate() {
+            let t = (step + 1) as f32 / (i - known) as f32;
+            *missing = Some(from.lerp(position, t));
+        }
+        known = i;
+    }
+    let mut max = f32::MIN;
+    colors
+        .into_iter()
+        .zip(positions)
+        .map(|((color, _), position)| {
+            max = max.max(position.unwrap());
+            (max, color)
+        })
+        .collect()
+}
+
+fn is_css_direction(arg: &str) -> bool {
+    arg.starts_with("to ")
+        || ["deg", "grad", "rad", "turn"]
+            .iter()
+            .any(|unit| arg.ends_with(unit) && arg[..arg.len() - unit.len()].parse::<f32>().is_ok())
+}
+
+fn css_position(position: &str) -> Result<f32, GradientImportError> {
+    let parsed = match position.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().map(|percent| percent / 100.0),
+        None if position == "0" => Ok(0.0),
+        None => return Err(GradientImportError::CssPosition(position.to_string())),
+    };
+    parsed.map_err(|_| GradientImportError::CssPosition(position.to_string()))
+}
+
+/// a CSS color: `#rgb`, `#rrggbbaa`, `rgb()`, `rgba()`, `hsl()`, `hsla()` or a basic color name
+pub fn css_color(color: &str) -> Result<GradientColor, GradientImportError> {
+    let color_error = || GradientImportError::CssColor(color.to_string());
+    let color = color.trim();
+    if color.starts_with('#') {
+        let srgba = Srgba::hex(color).map_err(|_| color_error())?;
+        return Ok(GradientColor::Srgb(srgba.to_u8_array()));
+    }
+    if let Some(open) = color.find('(') {
+        let name = color[..open].trim().to_ascii_lowercase();
+        let args = color[open + 1..]
+            .strip_suffix(')')
+            .ok_or_else(color_error)?;
+        // both "1, 2, 3, 0.5" and "1 2 3 / 50%"
+        let values: Vec<&str> = args
+            .split([',', ' ', '/'])
+            .filter(|value| !value.is_empty())
+            .collect();
+        if values.len() < 3 {
+            return Err(color_error());
+        }
+        let number = |value: &str, percent_scale: f32| -> Result<f32, GradientImportError> {
+            match value.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().map(|p| p / 100.0 * percent_scale),
+                None => value.trim_end_matches("deg").parse::<f32>(),
+            }
+            .map_err(|_| color_error())
+        };
+        let alpha = values
+            .get(3)
+            .map(|a| number(a, 1.0))
+            .transpose()?
+            .unwrap_or(1.0);
+        let srgba = match name.as_str() {
+            "rgb" | "rgba" => Srgba::new(
+                number(values[0], 255.0)? / 255.0,
+                number(values[1], 255.0)? / 255.0,
+                number(values[2], 255.0)? / 255.0,
+                alpha,
+            ),
+            "hsl" | "hsla" => Hsla::new(
+                number(values[0], 360.0)?.rem_euclid(360.0),
+                number(values[1], 1.0)?,
+                number(values[2], 1.0)?,
+                alpha,
+            )
+            .into(),
+            _ => return Err(color_error()),
+        };
+        return Ok(GradientColor::Srgb(srgba.to_u8_array()));
+    }
+    let named = match color.to_ascii_lowercase().as_str() {
+        "transparent" => Srgba::NONE,
+        "black" => css::BLACK,
+        "white" => css::WHITE,
+        "red" => css::RED,
+        "green" => css::GREEN,
+        "lime" => css::LIME,
+        "blue" => css::BLUE,
+        "yellow" => css::YELLOW,
+        "cyan" | "aqua" => css::AQUA,
+        "magenta" | "fuchsia" => css::FUCHSIA,
+        "gray" | "grey" => css::GRAY,
+        "silver" => css::SILVER,
+        "maroon" => css::MAROON,
+        "navy" => css::NAVY,
+        "olive" => css::OLIVE,
+        "purple" => css::PURPLE,
+        "teal" => css::TEAL,
+        "orange" => css::ORANGE,
+        _ => return Err(color_error()),
+    };
+    Ok(GradientColor::Srgb(named.to_u8_array()))
+}
+
+// index of the ")" closing an already opened parenthesis
+fn closing_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// split on `separator`, except inside parentheses
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// gradient from one row of pixels, one stop per pixel, left = 0.0 -> right = 1.0.
+/// float images become HDR colors
+pub fn gradient_from_image_row(image: &Image, row: u32) -> Result<Gradient, GradientImportError> {
+    let width = image.width();
+    if width == 0 {
+        return Err(GradientImportError::NoColors);
+    }
+    let hdr = utils::is_float_format(image.texture_descriptor.format);
+    let stops = (0..width)
+        .map(|x| {
+            let color = image.get_color_at(x, row)?;
+            let position = x as f32 / (width - 1).max(1) as f32;
+            let color = if hdr {
+                GradientColor::Hdr(color.to_linear())
+            } else {
+                GradientColor::Srgb(color.to_srgba().to_u8_array())
+            };
+            Ok((position, color))
+        })
+        .collect::<Result<Vec<_>, TextureAccessError>>()?;
+    Ok(Gradient::new(stops))
+}
+
+/// a whole sky from an image, 6 rows high, one row per time of day from the top:
+/// sunrise, day low, day high, sunset, night low, night high.
+/// every column is a sky color, from straight down (left) to straight up (right)
+pub fn sky_gradient_builder_from_image(
+    image: &Image,
+) -> Result<SkyGradientBuilder, GradientImportError> {
+    const ROWS: u32 = 6;
+    if image.height() != ROWS {
+        return Err(GradientImportError::SkyImageHeight {
+            expected: ROWS,
+            got: image.height(),
+        });
+    }
+    let rows = (0..ROWS)
+        .map(|row| gradient_from_image_row(image, row))
+        .collect::<Result<Vec<_>, _>>()?;
+    let stops = (0..image.width() as usize)
+        .map(|column| {
+            let color = |row: usize| rows[row].stops[column].1;
+            SkyGradientStop {
+                position: rows[0].stops[column].0,
+                colors: GradientBuilder {
+                    sunrise_color: color(0),
+                    day_low_color: color(1),
+                    day_high_color: color(2),
+                    sunset_color: color(3),
+                    night_low_color: color(4),
+                    night_high_color: color(5),
+                },
+                keyframes: None,
+                animated_position: None,
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(SkyGradientBuilder::new(stops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: GradientColor = GradientColor::Srgb([255, 0, 0, 255]);
+    const LIME: GradientColor = GradientColor::Srgb([0, 255, 0, 255]);
+    const BLUE: GradientColor = GradientColor::Srgb([0, 0, 255, 255]);
+    const WHITE: GradientColor = GradientColor::Srgb([255, 255, 255, 255]);
+
+    // as saved by GIMP 2.10: a linear rgb segment and a step segment
+    const GGR: &str = "GIMP Gradient
+Name: Red to blue
+2
+0.000000 0.250000 0.500000 1.000000 0.000000 0.000000 1.000000 0.000000 0.000000 1.000000 1.000000 0 0 0 0
+0.500000 0.750000 1.000000 0.000000 0.000000 1.000000 1.000000 1.000000 1.000000 1.000000 1.000000 5 0 0 0
+";
+
+    fn assert_stops(gradient: &Gradient, expected: &[(f32, GradientColor)]) {
+        assert_eq!(gradient.stops.len(), expected.len(), "{:?}", gradient.stops);
+        for ((position, color), (expected_position, expected_color)) in
+            gradient.stops.iter().zip(expected)
+        {
+            assert!(
+                (position - expected_position).abs() < 0.0001,
+                "{:?}",
+                gradient.stops
+            );
+            assert_eq!(color, expected_color, "{:?}", gradient.stops);
+        }
+    }
+
+    #[test]
+    fn ggr_file() {
+        let gradient = gradient_from_ggr(GGR).unwrap();
+        let purple = GradientColor::Srgb([128, 0, 128, 255]);
+        assert_stops(
+            &gradient,
+            &[
+                (0.0, RED),
+                (0.25, purple),
+                (0.5, BLUE),
+                (0.5, BLUE),
+                (0.75, BLUE),
+                (0.75, WHITE),
+                (1.0, WHITE),
+            ],
+        );
+
+        // the name line is optional, blend functions that aren't linear are sampled
+        let curved = "GIMP Gradient\n1\n0 0.5 1 0 0 0 1 1 1 1 1 1 0";
+        let gradient = gradient_from_ggr(curved).unwrap();
+        assert_eq!(gradient.stops.len(), GGR_SEGMENT_SAMPLES + 1);
+    }
+
+    #[test]
+    fn ggr_errors() {
+        let cases = [
+            ("Not A Gradient\n1", "not a GIMP gradient"),
+            ("GIMP Gradient\nName: x\nmany", "line 3"),
+            (
+                "GIMP Gradient\n2\n0 0.5 1 0 0 0 1 1 1 1 1 0 0",
+                "fewer segments",
+            ),
+            ("GIMP Gradient\n1\n0 0.5 1 0 0 0 1", "at least 13"),
+            ("GIMP Gradient\n1\n0 0.5 1 0 0 0 1 1 1 one 1 0 0", "numbers"),
+            ("GIMP Gradient\nName: empty", "at least one color"),
+        ];
+        for (text, expected) in cases {
+            let err = gradient_from_ggr(text).unwrap_err().to_string();
+            assert!(err.contains(expected), "{text:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn ggr_blend_factors() {
+        // (blend, middle, pos, factor)
+        let cases = [
+            // linear, the middle is half way
+            (0, 0.5, 0.25, 0.25),
+            (0, 0.25, 0.25, 0.5),
+            (0, 0.25, 0.625, 0.75),
+            // curved
+            (1, 0.5, 0.25, 0.25),
+            (1, 0.25, 0.25, 0.5),
+            // sine
+            (2, 0.5, 0.5, 0.5),
+            (2, 0.5, 0.25, 0.146_446_6),
+            // sphere increasing, sphere decreasing
+            (3, 0.5, 0.5, 0.866_025_4),
+            (4, 0.5, 0.5, 0.133_974_6),
+            // step
+            (5, 0.5, 0.49, 0.0),
+            (5, 0.5, 0.5, 1.0),
+            // unknown blend types fall back to linear
+            (9, 0.5, 0.25, 0.25),
+        ];
+        for (blend, middle, pos, expected) in cases {
+            let factor = ggr_blend_factor(blend, middle, pos);
+            assert!(
+                (factor - expected).abs() < 0.0001,
+                "blend {blend} middle {middle} pos {pos}: {factor}"
+            );
+        }
+        // every blend starts at the left color and ends at the right color
+        for blend in 0..=5 {
+            for middle in [0.2, 0.5, 0.8] {
+                assert!(ggr_blend_factor(blend, middle, 0.0).abs() < 0.0001);
+                assert!((ggr_blend_factor(blend, middle, 1.0) - 1.0).abs() < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn css_gradients() {
+        let cases: [(&str, &[(f32, GradientColor)]); 6] = [
+            ("linear-gradient(red, blue)", &[(0.0, RED), (1.0, BLUE)]),
+            // a solid band
+            (
+                "linear-gradient(90deg, red 10% 20%, blue)",
+                &[(0.1, RED), (0.2, RED), (1.0, BLUE)],
+            ),
+            // missing positions are spread between the known ones
+            (
+                "linear-gradient(to top, red, lime, blue 50%, white)",
+                &[(0.0, RED), (0.25, LIME), (0.5, BLUE), (1.0, WHITE)],
+            ),
+            // positions never go backwards
+            (
+                "linear-gradient(red 50%, blue 20%)",
+                &[(0.5, RED), (0.5, BLUE)],
+            ),
+            // a color hint is skipped
+            (
+                "linear-gradient(red, 30%, blue)",
+                &[(0.0, RED), (1.0, BLUE)],
+            ),
+            // copied from Figma
+            (
+                "background: linear-gradient(180deg, rgba(255, 0, 0, 1) 0%, #0000FF 100%);",
+                &[(0.0, RED), (1.0, BLUE)],
+            ),
+        ];
+        for (css, expected) in cases {
+            let gradient = gradient_from_css(css).unwrap();
+            assert_stops(&gradient, expected);
+        }
+    }
+
+    #[test]
+    fn css_errors() {
+        let cases = [
+            ("radial-gradient(red, blue)", "no linear-gradient"),
+            ("linear-gradient(red, blue", "unclosed parenthesis"),
+            ("linear-gradient(red 10px, blue)", "\"10px\""),
+            ("linear-gradient(redish, blue)", "unknown css color"),
+            ("linear-gradient(to right)", "at least one color"),
+        ];
+        for (css, expected) in cases {
+            let err = gradient_from_css(css).unwrap_err().to_string();
+            assert!(err.contains(expected), "{css:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn css_colors() {
+        let half_red = GradientColor::Srgb([255, 0, 0, 128]);
+        let cases = [
+            ("#f00", RED),
+            ("#ff000080", half_red),
+            ("Blue", BLUE),
+            // rgb, legacy and modern syntax
+            ("rgb(255, 0, 0)", RED),
+            ("rgba(255, 0, 0, 0.5)", half_red),
+            ("rgb(255 0 0)", RED),
+            ("rgb(255 0 0 / 50%)", half_red),
+            ("rgb(100% 0% 0% / 0.5)", half_red),
+            // hsl, legacy and modern syntax
+            ("hsl(120, 100%, 50%)", LIME),
+            ("hsla(0, 100%, 50%, 0.5)", half_red),
+            ("hsl(120deg 100% 50%)", LIME),
+            (
+                "hsl(-120 100% 50% / 50%)",
+                GradientColor::Srgb([0, 0, 255, 128]),
+            ),
+        ];
+        for (css, expected) in cases {
+            assert_eq!(css_color(css).unwrap(), expected, "{css}");
+        }
+        for css in [
+            "#ff",
+            "rgb(1, 2)",
+            "lab(1 2 3)",
+            "rgb(1, 2, 3",
+            "rgb(a, b, c)",
+        ] {
+            assert!(css_color(css).is_err(), "{css}");
+        }
+    }
+
+    #[test]
+    fn split_top_level_keeps_parentheses() {
+        let cases: [(&str, char, &[&str]); 4] = [
+            ("a, b", ',', &["a", " b"]),
+            (
+                "rgb(1, 2, 3) 10%, hsl(1, 2%, 3%)",
+                ',',
+                &["rgb(1, 2, 3) 10%", " hsl(1, 2%, 3%)"],
+            ),
+            ("f(g(1, 2), 3), 4", ',', &["f(g(1, 2), 3)", " 4"]),
+            ("rgb(1 2 3) 10% 20%", ' ', &["rgb(1 2 3)", "10%", "20%"]),
+        ];
+        for (text, separator, expected) in cases {
+            assert_eq!(split_top_level(text, separator), expected, "{text}");
+        }
+    }
+}
@@ -3,26 +3,55 @@ use bevy::{
     pbr::{Material, MaterialPipeline, MaterialPipelineKey},
     prelude::*,
     reflect::Reflect,
-    render::render_resource::{
-        AsBindGroup, CompareFunction, RenderPipelineDescriptor, SpecializedMeshPipelineError,
+    render::{
+        render_resource::{
+            AsBindGroup, CompareFunction, RenderPipelineDescriptor, SpecializedMeshPipelineError,
+        },
+        storage::ShaderStorageBuffer,
     },
     shader::ShaderRef,
 };
 
-use crate::bind_groups::GradientBindGroup;
+use crate::bind_groups::{GpuGradientStop, GradientBindGroup, GroundBindGroup};
 
 pub struct GradientMaterialPlugin;
 
 impl Plugin for GradientMaterialPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<FullGradientMaterial>::default());
+        app.add_systems(PostUpdate, upload_gradient_stops);
     }
 }
 
-#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+/// renders the sky gradient, any number of color stops.
+/// requires storage buffers, which WebGL2 doesn't support
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
 pub struct FullGradientMaterial {
-    #[uniform(0)]
-    pub gradient_bind_group: crate::bind_groups::GradientBindGroup,
+    /// edit this, it's copied into `stops_buffer` whenever the material changes
+    pub gradient_bind_group: GradientBindGroup,
+    /// created automatically if missing
+    #[storage(0, read_only)]
+    pub stops_buffer: Handle<ShaderStorageBuffer>,
+    /// 1 = read the colors from `lut_image` instead, at the cycle percent stored in the MeshTag.
+    /// auto set by [`crate::gradient_driver::GradientDriverMode::BakedLut`]
+    #[uniform(1)]
+    pub feature_lut_enabled: i32,
+    /// the whole day baked into a texture, x = cycle percent, y = view height
+    #[texture(2, dimension = "2d")]
+    #[sampler(3)]
+    pub lut_image: Option<Handle<Image>>,
+    /// colors below the horizon, 0.0 = straight down, 1.0 = the horizon.
+    /// copied into `ground_stops_buffer` whenever the material changes
+    pub ground_gradient_bind_group: GradientBindGroup,
+    /// created automatically if missing
+    #[storage(5, read_only)]
+    pub ground_stops_buffer: Handle<ShaderStorageBuffer>,
+    /// 1 = blend the ground colors in below the horizon.
+    /// auto set by [`crate::gradient_driver::GradientDriverPlugin::ground_colors_builder`]
+    #[uniform(6)]
+    pub feature_ground_enabled: i32,
+    #[uniform(7)]
+    pub ground: GroundBindGroup,
 }
 
 impl Material for FullGradientMaterial {
@@ -48,20 +77,54 @@ impl Material for FullGradientMaterial {
     }
 }
 
-impl Default for FullGradientMaterial {
-    fn default() -> Self {
-        let color_stops = [
-            Vec4::new(0.2, 0.3, 0.6, 1.0),
-            Vec4::new(0.4, 0.5, 1.0, 1.0),
-            Vec4::new(0.35, 0.6, 0.8, 1.0),
-            Vec4::new(0.5, 0.7, 1.0, 1.0),
-        ];
-        FullGradientMaterial {
-            gradient_bind_group: GradientBindGroup {
-                color_stops,
-                positions: Vec4::new(0.38, 0.47, 0.61, 1.0),
-                num_stops: 4,
-            },
+// the stop count can change at any time, so the stops live in a storage buffer
+pub(crate) fn upload_gradient_stops(
+    mut events: MessageReader<AssetEvent<FullGradientMaterial>>,
+    mut materials: ResMut<Assets<FullGradientMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    for event in events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::Modified { id }) = *event else {
+            continue;
+        };
+        // read only access first, mutating the material would trigger another Modified event
+        let Some(material) = materials.get(id) else {
+            continue;
+        };
+        let stops = upload_stops(
+            &mut buffers,
+            &material.stops_buffer,
+            material.gradient_bind_group.gpu_stops(),
+        );
+        let ground_stops = upload_stops(
+            &mut buffers,
+            &material.ground_stops_buffer,
+            material.ground_gradient_bind_group.gpu_stops(),
+        );
+        if stops.is_none() && ground_stops.is_none() {
+            continue;
+        }
+        let Some(material) = materials.get_mut(id) else {
+            continue;
+        };
+        if let Some(stops) = stops {
+            material.stops_buffer = buffers.add(ShaderStorageBuffer::from(stops));
+        }
+        if let Some(ground_stops) = ground_stops {
+            material.ground_stops_buffer = buffers.add(ShaderStorageBuffer::from(ground_stops));
         }
     }
 }
+
+// gives the stops back if there is no buffer to write them into yet
+fn upload_stops(
+    buffers: &mut Assets<ShaderStorageBuffer>,
+    buffer: &Handle<ShaderStorageBuffer>,
+    stops: Vec<GpuGradientStop>,
+) -> Option<Vec<GpuGradientStop>> {
+    let Some(buffer) = buffers.get_mut(buffer) else {
+        return Some(stops);
+    };
+    buffer.set_data(stops);
+    None
+}
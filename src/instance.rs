@@ -0,0 +1,423 @@
+use bevy::{camera::visibility::RenderLayers, prelude::*, transform::plugins::TransformSystems};
+
+use crate::{
+    ambient_driver::{AmbientColors, AmbientColorsBuilder, AmbientSettings},
+    cycle::{SkyTime, SkyTimeSettings, SkyTimeWarp},
+    gradient::{SkyGradientBuilder, SkyGradients},
+    gradient_material::FullGradientMaterial,
+    noise::NoiseHandles,
+    plugin::{AuroraTextureHandle, SkySettings},
+    sky_material::FullSkyMaterial,
+    sun::{SunPath, SunSettings},
+    utils,
+};
+
+/// runs every camera with a [`SkyInstance`]
+/// added by SkyPlugin, does nothing until a SkyInstance is spawned
+pub struct SkyInstancePlugin;
+
+impl Plugin for SkyInstancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_sky_instance_rigs,
+                tick_sky_instances,
+                rebuild_sky_instance_gradients,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                despawn_orphan_sky_instance_rigs,
+                drive_sky_instances,
+                resize_sky_instance_gradients,
+                sky_instance_rig_follow_camera.before(TransformSystems::Propagate),
+            ),
+        );
+    }
+}
+
+/// attach to a camera to give it its own sky, with its own time, colors and materials.
+/// the time, timings and warp are the SkyTime, SkyTimeSettings and SkyTimeWarp components on the same camera.
+///
+/// the global resources (SkyTime, SkyGradients...) still drive the default skybox.
+/// once a SkyInstance exists, the default skybox moves to
+/// [`SkySettings::magnet_rig_render_layer`], so this camera doesn't draw it.
+/// if every camera has its own SkyInstance, disable the default skybox with
+/// `SkyPluginBuilder::set_spawn_default_skybox(false)` and skip the global drivers.
+///
+/// the aurora is only rendered for the default sky, and the directional sun light stays global.
+#[derive(Component, Clone)]
+#[require(SkyTime, SkyTimeSettings, SkyTimeWarp)]
+pub struct SkyInstance {
+    pub sky_colors_builder: SkyGradientBuilder,
+    pub ambient_colors_builder: AmbientColorsBuilder,
+    pub ambient_settings: AmbientSettings,
+    pub sun_settings: SunSettings,
+    pub sun_path: SunPath,
+    /// the skybox of this instance renders on this layer, it's added to the camera's RenderLayers.
+    /// must be unique per instance
+    pub sky_render_layer: usize,
+    /// the helper camera rendering the gradient of this instance renders on this layer.
+    /// must be unique per instance
+    pub gradient_render_layer: usize,
+}
+
+impl SkyInstance {
+    pub fn new(sky_render_layer: usize, gradient_render_layer: usize) -> Self {
+        Self {
+            sky_colors_builder: SkyGradientBuilder::default(),
+            ambient_colors_builder: AmbientColorsBuilder::default(),
+            ambient_settings: AmbientSettings::default(),
+            sun_settings: SunSettings::default(),
+            sun_path: SunPath::default(),
+            sky_render_layer,
+            gradient_render_layer,
+        }
+    }
+}
+
+/// the entities spawned for a SkyInstance, lives on the SkyInstance camera
+#[derive(Component, Clone)]
+pub struct SkyInstanceRig {
+    /// the layer added to the camera's RenderLayers, removed with the rig
+    pub sky_render_layer: usize,
+    pub skybox: Entity,
+    pub gradient_mesh: Entity,
+    pub gradient_camera: Entity,
+    pub gradient_texture: Handle<Image>,
+}
+
+/// marks an entity as part of the sky rig of a SkyInstance camera
+/// global sky systems ignore these entities
+#[derive(Component, Clone, Copy)]
+pub struct SkyInstanceOf(pub Entity);
+
+type NewSkyInstances<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static SkyInstance,
+        &'static SkyTimeSettings,
+        Option<&'static RenderLayers>,
+    ),
+    Added<SkyInstance>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_sky_instance_rigs(
+    mut commands: Commands,
+    new_instances: NewSkyInstances,
+    cameras: Query<&Camera>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut gradient_materials: ResMut<Assets<FullGradientMaterial>>,
+    mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
+    noise_handles: Res<NoiseHandles>,
+    aurora_handles: Res<AuroraTextureHandle>,
+    sky_settings: Res<SkySettings>,
+) {
+    let mut taken_orders = utils::camera_orders(&cameras);
+    for (camera_entity, instance, sky_time_settings, render_layers) in &new_instances {
+        let magnet_layers = sky_settings.magnet_rig_render_layer;
+        if instance.sky_render_layer >= magnet_layers
+            || instance.gradient_render_layer >= magnet_layers
+        {
+            warn!(
+                "SkyPlugin: SkyInstance layers overlap the MagnetSkyRig layers, \
+                 starting at SkySettings::magnet_rig_render_layer {magnet_layers}"
+            );
+        }
+        let mut spawner = utils::RigSpawner {
+            commands: &mut commands,
+            meshes: &mut meshes,
+            images: &mut images,
+            owner: SkyInstanceOf(camera_entity),
+            name: "sky_instance",
+            taken_orders: &mut taken_orders,
+        };
+        // no aurora, it's only rendered for the default sky
+        let gradient = spawner.texture_pass(
+            "gradient",
+            gradient_materials.add(FullGradientMaterial::default()),
+            instance.gradient_render_layer,
+            // after the default gradient camera
+            sky_settings.camera_gradient_order + 1..,
+            sky_settings.gradient_texture_format(),
+            sky_settings.gradient_camera_hdr(),
+        );
+        let skybox = spawner.skybox(
+            sky_materials.add(FullSkyMaterial {
+                noise3_image: noise_handles.noise3.clone(),
+                voronoi3_image: noise_handles.voronoi3.clone(),
+                aurora_image: aurora_handles.render_target.clone(),
+                gradient_image: gradient.texture.clone(),
+                stars: sky_settings.stars_bind_group.clone(),
+                feature_aurora_enabled: 0,
+                ..default()
+            }),
+            instance.sky_render_layer,
+        );
+
+        // the camera must see its own skybox, on top of what it already renders
+        let camera_layers = render_layers
+            .cloned()
+            .unwrap_or_default()
+            .with(instance.sky_render_layer);
+
+        commands.entity(camera_entity).insert((
+            SkyInstanceRig {
+                sky_render_layer: instance.sky_render_layer,
+                skybox,
+                gradient_mesh: gradient.mesh,
+                gradient_camera: gradient.camera,
+                gradient_texture: gradient.texture,
+            },
+            camera_layers,
+            instance.sky_colors_builder.build(sky_time_settings),
+            instance.ambient_colors_builder.build(sky_time_settings),
+            AmbientLight::default(),
+        ));
+    }
+}
+
+fn tick_sky_instances(
+    time: Res<Time>,
+    mut instances: Query<(&mut SkyTime, &mut SkyTimeSettings), With<SkyInstance>>,
+) {
+    for (mut sky_time, mut sky_time_settings) in &mut instances {
+        if sky_time_settings.is_changed()
+            && let Err(err) = sky_time_settings.validate()
+        {
+            warn!("SkyPlugin: invalid SkyInstance SkyTimeSettings, clamping. reason: {err}");
+            let clamped = sky_time_settings.clamped();
+            *sky_time_settings = clamped;
+        }
+        sky_time.tick(time.delta_secs(), &sky_time_settings);
+    }
+}
+
+type ChangedSkyInstances<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static SkyInstance,
+        &'static SkyTimeSettings,
+        &'static mut SkyGradients,
+        &'static mut AmbientColors,
+    ),
+    Or<(Changed<SkyInstance>, Changed<SkyTimeSettings>)>,
+>;
+
+// gradients change when the colors or timings of an instance change
+fn rebuild_sky_instance_gradients(mut instances: ChangedSkyInstances) {
+    for (instance, sky_time_settings, mut sky_colors, mut ambient_colors) in &mut instances {
+        *sky_colors = instance.sky_colors_builder.build(sky_time_settings);
+        *ambient_colors = instance.ambient_colors_builder.build(sky_time_settings);
+    }
+}
+
+type DrivenSkyInstances<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static SkyInstance,
+        &'static SkyTime,
+        &'static SkyTimeSettings,
+        &'static SkyTimeWarp,
+        &'static SkyGradients,
+        &'static AmbientColors,
+        &'static SkyInstanceRig,
+        &'static mut AmbientLight,
+    ),
+>;
+
+/// same as the global drivers, but per instance: gradient, sun, night time and ambient
+fn drive_sky_instances(
+    mut instances: DrivenSkyInstances,
+    gradient_meshes: Query<&MeshMaterial3d<FullGradientMaterial>, With<SkyInstanceOf>>,
+    skyboxes: Query<&MeshMaterial3d<FullSkyMaterial>, With<SkyInstanceOf>>,
+    mut gradient_materials: ResMut<Assets<FullGradientMaterial>>,
+    mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
+) {
+    for (
+        instance,
+        sky_time,
+        sky_time_settings,
+        sky_time_warp,
+        sky_colors,
+        ambient_colors,
+        rig,
+        mut ambient,
+    ) in &mut instances
+    {
+        let time = sky_time_warp.warp_time(sky_time_settings, sky_time.time);
+        let (look_at_sun, _) = instance.sun_path.sun_orientation(sky_time_settings, time);
+
+        let time = instance.sun_path.gradient_time(sky_time_settings, time);
+        let percent = sky_time_settings.time_percent(time);
+
+        if let Ok(handle) = gradient_meshes.get(rig.gradient_mesh)
+            && let Some(sampled) = gradient_materials.get(handle).and_then(|material| {
+                sky_colors.sample_changed(percent, &material.gradient_bind_group)
+            })
+            && let Some(gradient_material) = gradient_materials.get_mut(handle)
+        {
+            gradient_material.gradient_bind_group = sampled;
+        }
+
+        if let Ok(handle) = skyboxes.get(rig.skybox)
+            && let Some(sky_material) = sky_materials.get_mut(handle)
+        {
+            sky_material.sun.sun_dir = look_at_sun;
+            sky_material.sun.sun_color = instance.sun_settings.sun_color;
+            sky_material.sun.sun_strength = instance.sun_settings.sun_strength;
+            sky_material.sun.sun_sharpness = instance.sun_settings.sun_sharpness;
+            sky_material.horizon_glow = instance.sun_settings.horizon_glow_towards(look_at_sun);
+            sky_material.night_time_distance = sky_time_settings.night_time_distance(time);
+        }
+
+        ambient_colors.sample_into(percent, &instance.ambient_settings, &mut ambient);
+    }
+}
+
+// the gradient texture should match the size of the camera's viewport
+fn resize_sky_instance_gradients(
+    instances: Query<(&Camera, &SkyInstanceRig)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (camera, rig) in &instances {
+        let Some(size) = camera.physical_viewport_size() else {
+            continue;
+        };
+        utils::resize_render_target(&mut images, &rig.gradient_texture, size);
+    }
+}
+
+fn sky_instance_rig_follow_camera(
+    instances: Query<(&Transform, &Projection, &SkyInstanceRig), With<SkyInstance>>,
+    mut rig_parts: Query<(&mut Transform, Option<&mut Projection>), Without<SkyInstance>>,
+) {
+    for (camera_transform, camera_projection, rig) in &instances {
+        for mesh in [rig.skybox, rig.gradient_mesh] {
+            if let Ok((mut transform, _)) = rig_parts.get_mut(mesh) {
+                transform.translation = camera_transform.translation;
+            }
+        }
+        if let Ok((mut transform, Some(mut projection))) = rig_parts.get_mut(rig.gradient_camera) {
+            *transform = *camera_transform;
+            *projection = camera_projection.clone();
+        }
+    }
+}
+
+// the SkyInstance was removed, or the camera despawned.
+// the camera gets back the layers and components it had before spawn_sky_instance_rigs
+fn despawn_orphan_sky_instance_rigs(
+    mut commands: Commands,
+    rig_parts: Query<(Entity, &SkyInstanceOf)>,
+    instances: Query<(), With<SkyInstance>>,
+    orphan_cameras: Query<(Entity, &SkyInstanceRig, Option<&RenderLayers>), Without<SkyInstance>>,
+) {
+    for (entity, owner) in &rig_parts {
+        if !instances.contains(owner.0) {
+            commands.entity(entity).try_despawn();
+        }
+    }
+    for (camera, rig, render_layers) in &orphan_cameras {
+        let mut camera_commands = commands.entity(camera);
+        camera_commands.try_remove::<(SkyInstanceRig, SkyGradients, AmbientColors, AmbientLight)>();
+        if let Some(render_layers) = render_layers {
+            camera_commands.try_insert(render_layers.clone().without(rig.sky_render_layer));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::storage::ShaderStorageBuffer;
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_asset::<ShaderStorageBuffer>()
+            .init_asset::<FullSkyMaterial>()
+            .init_asset::<FullGradientMaterial>()
+            .insert_resource(SkySettings::default())
+            .insert_resource(NoiseHandles {
+                noise3: Handle::default(),
+                voronoi3: Handle::default(),
+            })
+            .insert_resource(AuroraTextureHandle {
+                render_target: Handle::default(),
+            })
+            .add_plugins(SkyInstancePlugin);
+        app
+    }
+
+    #[test]
+    fn removing_the_instance_restores_the_camera() {
+        let mut app = app();
+        let camera = app
+            .world_mut()
+            .spawn((
+                Camera3d::default(),
+                RenderLayers::layer(0),
+                SkyInstance::new(3, 4),
+            ))
+            .id();
+        app.update();
+        let layers = app.world().get::<RenderLayers>(camera).unwrap();
+        assert!(*layers == RenderLayers::from_layers(&[0, 3]));
+        assert!(app.world().get::<SkyInstanceRig>(camera).is_some());
+
+        app.world_mut().entity_mut(camera).remove::<SkyInstance>();
+        app.update();
+        let world = app.world();
+        assert!(*world.get::<RenderLayers>(camera).unwrap() == RenderLayers::layer(0));
+        assert!(world.get::<SkyInstanceRig>(camera).is_none());
+        assert!(world.get::<SkyGradients>(camera).is_none());
+        assert!(world.get::<AmbientColors>(camera).is_none());
+        assert!(world.get::<AmbientLight>(camera).is_none());
+        let parts = app
+            .world_mut()
+            .query::<&SkyInstanceOf>()
+            .iter(app.world())
+            .count();
+        assert_eq!(parts, 0);
+    }
+
+    #[test]
+    fn gradient_cameras_get_free_orders() {
+        let mut app = app();
+        let order = SkySettings::default().camera_gradient_order + 1;
+        // a user camera already renders at the first gradient order
+        app.world_mut().spawn(Camera { order, ..default() });
+        let first = app
+            .world_mut()
+            .spawn((Camera3d::default(), SkyInstance::new(3, 4)))
+            .id();
+        let second = app
+            .world_mut()
+            .spawn((Camera3d::default(), SkyInstance::new(5, 6)))
+            .id();
+        app.update();
+        let gradient_order = |camera: Entity| {
+            let rig = app.world().get::<SkyInstanceRig>(camera).unwrap();
+            app.world()
+                .get::<Camera>(rig.gradient_camera)
+                .unwrap()
+                .order
+        };
+        let (first, second) = (gradient_order(first), gradient_order(second));
+        assert!(first > order && second > order);
+        assert_ne!(first, second);
+    }
+}
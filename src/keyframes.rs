@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cycle::SkyTimeSettings,
+    gradient::{Gradient, GradientColor},
+};
+
+/// when a keyframe happens, relative to the sunrise or sunset.
+/// keeps its place in the day when the [`SkyTimeSettings`] change
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum KeyTime {
+    /// offset from the middle of the sunrise, in sunrise durations.
+    /// -1.0 = the sunrise starts, 1.0 = the sunrise ends
+    Sunrise(f32),
+    /// offset from the middle of the sunset, in sunset durations.
+    /// -1.0 = the sunset starts, 1.0 = the sunset ends
+    Sunset(f32),
+    /// 0.0 = sunrise -> 1.0 = sunset
+    Day(f32),
+    /// 0.0 = sunset -> 1.0 = the next sunrise
+    Night(f32),
+}
+
+impl KeyTime {
+    /// the cycle percent 0.0 -> 1.0 this key happens at
+    pub fn percent(&self, s: &SkyTimeSettings) -> f32 {
+        let wrapped_percent = |time: f32| s.time_percent(time.rem_euclid(s.total_time()));
+        match *self {
+            KeyTime::Sunrise(offset) => wrapped_percent(offset * s.sunrise_time_sec),
+            KeyTime::Sunset(offset) => wrapped_percent(s.day_time_sec + offset * s.sunset_time_sec),
+            KeyTime::Day(percent) => percent.clamp(0.0, 1.0) * 0.5,
+            KeyTime::Night(percent) => 0.5 + percent.clamp(0.0, 1.0) * 0.5,
+        }
+    }
+}
+
+/// a named color at a time of day, "golden hour" for example
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct GradientKeyframe {
+    pub name: String,
+    pub time: KeyTime,
+    pub color: GradientColor,
+}
+
+/// any number of keyframes over the day, an alternative to [`crate::gradient::GradientBuilder`].
+/// the gradient loops, the last keyframe blends into the first one
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct KeyframeGradientBuilder {
+    pub keyframes: Vec<GradientKeyframe>,
+}
+
+impl KeyframeGradientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(
+        mut self,
+        name: impl Into<String>,
+        time: KeyTime,
+        color: impl Into<GradientColor>,
+    ) -> Self {
+        self.keyframes.push(GradientKeyframe {
+            name: name.into(),
+            time,
+            color: color.into(),
+        });
+        self
+    }
+
+    pub fn keyframe(&self, name: &str) -> Option<&GradientKeyframe> {
+        self.keyframes.iter().find(|keyframe| keyframe.name == name)
+    }
+
+    pub fn keyframe_mut(&mut self, name: &str) -> Option<&mut GradientKeyframe> {
+        self.keyframes
+            .iter_mut()
+            .find(|keyframe| keyframe.name == name)
+    }
+
+    pub fn build_gradient(&self, s: &SkyTimeSettings) -> Gradient {
+        let mut stops: Vec<(f32, GradientColor)> = self
+            .keyframes
+            .iter()
+            .map(|keyframe| (keyframe.time.percent(s), keyframe.color))
+            .collect();
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        // repeat the ends one cycle away, so the day loops without a seam
+        if let (Some(first), Some(last)) = (stops.first().copied(), stops.last().copied()) {
+            stops.insert(0, (last.0 - 1.0, last.1));
+            stops.push((first.0 + 1.0, first.1));
+        }
+        Gradient::new(stops)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        for i in 0..4 {
+            assert!((a[i] - b[i]).abs() < 0.01, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn key_time_percent() {
+        // day 15, night 25, sunrise 2, sunset 2
+        let s = SkyTimeSettings::default();
+        let cases = [
+            (KeyTime::Sunrise(0.0), 0.0),
+            (KeyTime::Sunrise(1.0), 2.0 / 15.0 * 0.5),
+            // before the sunrise is the end of the previous night
+            (KeyTime::Sunrise(-1.0), 0.5 + 23.0 / 25.0 * 0.5),
+            (KeyTime::Sunset(0.0), 0.5),
+            (KeyTime::Sunset(-1.0), 13.0 / 15.0 * 0.5),
+            (KeyTime::Sunset(1.0), 0.5 + 2.0 / 25.0 * 0.5),
+            (KeyTime::Day(0.5), 0.25),
+            (KeyTime::Day(2.0), 0.5),
+            (KeyTime::Night(0.5), 0.75),
+            (KeyTime::Night(-1.0), 0.5),
+        ];
+        for (key_time, expected) in cases {
+            let percent = key_time.percent(&s);
+            assert!(
+                (percent - expected).abs() < 0.0001,
+                "{key_time:?}: {percent}"
+            );
+        }
+    }
+
+    #[test]
+    fn key_time_follows_settings() {
+        let short_night = SkyTimeSettings {
+            night_time_sec: 5.0,
+            ..default()
+        };
+        // the sunrise starts 2 seconds before the end of the night, whatever its length
+        let percent = KeyTime::Sunrise(-1.0).percent(&short_night);
+        assert!(
+            (percent - (0.5 + 3.0 / 5.0 * 0.5)).abs() < 0.0001,
+            "{percent}"
+        );
+    }
+
+    #[test]
+    fn wraps_across_the_day_boundary() {
+        let s = SkyTimeSettings::default();
+        let builder = KeyframeGradientBuilder::new()
+            .with_key("night", KeyTime::Night(0.5), BLUE)
+            .with_key("sunrise", KeyTime::Sunrise(0.5), RED);
+        let gradient = builder.build_gradient(&s);
+
+        // night at 0.75 blends into the sunrise after the boundary
+        let (night, sunrise) = (0.75, KeyTime::Sunrise(0.5).percent(&s) + 1.0);
+        let red = (1.0 - night) / (sunrise - night);
+        let expected = [red, 0.0, 1.0 - red, 1.0];
+        assert_close(gradient.sample_at(0.0), expected);
+        assert_close(gradient.sample_at(1.0), expected);
+        // either side of the boundary samples the same blend
+        assert_close(gradient.sample_at(0.999), gradient.sample_at(0.001));
+        assert_close(gradient.sample_at(0.75), [0.0, 0.0, 1.0, 1.0]);
+        assert_close(gradient.sample_at(sunrise - 1.0), [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn single_key_is_flat() {
+        let gradient = KeyframeGradientBuilder::new()
+            .with_key("noon", KeyTime::Day(0.5), RED)
+            .build_gradient(&SkyTimeSettings::default());
+        for t in [0.0, 0.25, 0.6, 1.0] {
+            assert_close(gradient.sample_at(t), [1.0, 0.0, 0.0, 1.0]);
+        }
+    }
+}
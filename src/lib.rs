@@ -5,22 +5,42 @@ pub mod aurora_material;
 pub mod bind_groups;
 pub mod cycle;
 pub mod gradient;
+#[cfg(feature = "serde")]
+pub mod gradient_assets;
 pub mod gradient_driver;
+pub mod gradient_import;
 pub mod gradient_material;
+pub mod instance;
+pub mod keyframes;
+pub mod magnet_rig;
 pub mod noise;
 pub mod plugin;
 pub mod presets;
+pub mod season;
 pub mod sky_material;
 pub mod sky_texture;
+#[cfg(feature = "serde")]
+pub mod state;
 pub mod sun;
+pub mod time_source;
 pub mod utils;
 
 pub mod prelude {
     pub use crate::ambient_driver::{AmbientDriverPlugin, AmbientSettings};
     pub use crate::aurora::{AuroraPlugin, AuroraSettings};
-    pub use crate::cycle::{SkyCyclePlugin, SkyTime, SkyTimeSettings};
-    pub use crate::gradient_driver::GradientDriverPlugin;
+    pub use crate::cycle::{
+        DayPhase, SkyCyclePlugin, SkyPhaseChanged, SkyTime, SkyTimeSettings, SkyTimeSettingsError,
+        SkyTimeTransition, SkyTimeWarp, TimeOfDay,
+    };
+    pub use crate::gradient_driver::{GradientDriverMode, GradientDriverPlugin};
+    pub use crate::instance::SkyInstance;
     pub use crate::noise::{NoisePlugin, NoiseSettings};
     pub use crate::plugin::{SkyPlugin, SkySettings, SkyboxMagnetTag};
-    pub use crate::sun::{SunDriverPlugin, SunDriverTag, SunSettings};
+    pub use crate::season::{SeasonPlugin, SeasonSettings};
+    #[cfg(feature = "serde")]
+    pub use crate::state::SkyState;
+    pub use crate::sun::{
+        AstronomicalSunSettings, SunDriverPlugin, SunDriverTag, SunPath, SunSettings,
+    };
+    pub use crate::time_source::{SkyTimeAuthority, SkyTimeSnapshot, SkyTimeSource};
 }
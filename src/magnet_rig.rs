@@ -0,0 +1,524 @@
+use bevy::{camera::visibility::RenderLayers, prelude::*, transform::plugins::TransformSystems};
+
+use crate::{
+    aurora::AuroraSettings,
+    aurora_material::AuroraMaterial,
+    gradient_material::FullGradientMaterial,
+    instance::{self, SkyInstance, SkyInstanceRig},
+    plugin::{DefaultSkyFilter, PrimaryMagnetFilter, SkySettings, SkyboxMagnetTag},
+    sky_material::{self, FullSkyMaterial},
+    utils,
+};
+
+/// gives every extra [`SkyboxMagnetTag`] camera its own sky, for split screen.
+/// the first camera uses the default skybox, the others get a [`MagnetSkyRig`].
+/// all of them share the global time, colors and materials.
+/// added by SkyPlugin, does nothing while there is only one SkyboxMagnetTag camera
+pub struct MagnetRigPlugin;
+
+impl Plugin for MagnetRigPlugin {
+    fn build(&self, app: &mut App) {
+        // sees the SkyInstance rig cameras, their orders are taken
+        app.add_systems(
+            Update,
+            assign_magnet_rigs.after(instance::spawn_sky_instance_rigs),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                despawn_orphan_magnet_rigs,
+                share_default_skybox_layer,
+                sync_magnet_rig_skyboxes.after(sky_material::sync_inline_gradient),
+                resize_magnet_rigs,
+                magnet_rig_follow_camera.before(TransformSystems::Propagate),
+            ),
+        );
+    }
+}
+
+/// the entities rendering the sky of an extra SkyboxMagnetTag camera, lives on that camera
+#[derive(Component, Clone)]
+pub struct MagnetSkyRig {
+    /// which render layers the rig uses, see [`SkySettings::magnet_rig_render_layer`]
+    pub slot: usize,
+    pub skybox: Entity,
+    /// None with [`SkySettings::inline_gradient`]
+    pub gradient_mesh: Option<Entity>,
+    pub gradient_camera: Option<Entity>,
+    pub gradient_texture: Option<Handle<Image>>,
+    /// None without the aurora plugin
+    pub aurora_mesh: Option<Entity>,
+    pub aurora_camera: Option<Entity>,
+    pub aurora_texture: Option<Handle<Image>>,
+}
+
+impl MagnetSkyRig {
+    fn meshes(&self) -> impl Iterator<Item = Entity> {
+        [Some(self.skybox), self.gradient_mesh, self.aurora_mesh]
+            .into_iter()
+            .flatten()
+    }
+
+    fn cameras(&self) -> impl Iterator<Item = Entity> {
+        [self.gradient_camera, self.aurora_camera]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// marks an entity as part of the [`MagnetSkyRig`] of a camera
+/// global sky systems ignore these entities
+#[derive(Component, Clone, Copy)]
+pub struct MagnetRigOf(pub Entity);
+
+/// the render layers of a rig slot: skybox, gradient, aurora
+fn slot_layers(sky_settings: &SkySettings, slot: usize) -> (usize, usize, usize) {
+    let first = sky_settings.magnet_rig_render_layer + 1 + slot * 3;
+    (first, first + 1, first + 2)
+}
+
+type MagnetCameras<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Option<&'static MagnetSkyRig>,
+        Option<&'static RenderLayers>,
+    ),
+    (With<SkyboxMagnetTag>, With<Camera>, Without<SkyInstance>),
+>;
+
+// the first camera without a rig keeps the default skybox, every other camera gets a rig
+#[allow(clippy::too_many_arguments)]
+fn assign_magnet_rigs(
+    mut commands: Commands,
+    cameras: MagnetCameras,
+    all_cameras: Query<&Camera>,
+    default_skyboxes: Query<&MeshMaterial3d<FullSkyMaterial>, DefaultSkyFilter>,
+    default_gradients: Query<&MeshMaterial3d<FullGradientMaterial>, DefaultSkyFilter>,
+    default_auroras: Query<&MeshMaterial3d<AuroraMaterial>, Without<MagnetRigOf>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
+    sky_settings: Res<SkySettings>,
+    aurora_settings: Option<Res<AuroraSettings>>,
+) {
+    let mut cameras: Vec<_> = cameras.iter().collect();
+    // stable between frames, usually the camera spawned first
+    cameras.sort_by_key(|(entity, _, _)| entity.index());
+    let mut primary = cameras
+        .iter()
+        .find(|(_, rig, _)| rig.is_none())
+        .map(|(entity, _, _)| *entity);
+    // the primary camera went away, a rigged camera takes over the default skybox
+    if primary.is_none()
+        && let Some((entity, Some(rig), layers)) = cameras.first()
+    {
+        remove_magnet_rig(&mut commands, *entity, rig, *layers, &sky_settings);
+        primary = Some(*entity);
+    }
+    if cameras.iter().filter(|(_, rig, _)| rig.is_none()).count() < 2 {
+        return;
+    }
+    let Ok(default_skybox) = default_skyboxes.single() else {
+        return;
+    };
+    let Some(default_sky_material) = sky_materials.get(default_skybox).cloned() else {
+        return;
+    };
+
+    let mut taken_orders = utils::camera_orders(&all_cameras);
+    let mut used_slots: Vec<usize> = cameras
+        .iter()
+        .filter_map(|(_, rig, _)| rig.map(|rig| rig.slot))
+        .collect();
+    for (camera_entity, _, render_layers) in cameras
+        .iter()
+        .filter(|(entity, rig, _)| rig.is_none() && Some(*entity) != primary)
+    {
+        let slot = (0..).find(|slot| !used_slots.contains(slot)).unwrap_or(0);
+        used_slots.push(slot);
+        let (sky_layer, gradient_layer, aurora_layer) = slot_layers(&sky_settings, slot);
+        let mut sky_material = default_sky_material.clone();
+        let mut spawner = utils::RigSpawner {
+            commands: &mut commands,
+            meshes: &mut meshes,
+            images: &mut images,
+            owner: MagnetRigOf(*camera_entity),
+            name: "magnet_rig",
+            taken_orders: &mut taken_orders,
+        };
+
+        // the default gradient material, sampled or baked by the global drivers
+        let gradient = default_gradients
+            .single()
+            .ok()
+            .filter(|_| !sky_settings.inline_gradient)
+            .map(|gradient_material| {
+                spawner.texture_pass(
+                    "gradient",
+                    gradient_material.0.clone(),
+                    gradient_layer,
+                    // after the default gradient camera
+                    sky_settings.camera_gradient_order + 1..,
+                    sky_settings.gradient_texture_format(),
+                    sky_settings.gradient_camera_hdr(),
+                )
+            });
+        if let Some(gradient) = &gradient {
+            sky_material.gradient_image = gradient.texture.clone();
+        }
+
+        let aurora = aurora_settings
+            .as_ref()
+            .zip(default_auroras.single().ok())
+            .map(|(aurora_settings, aurora_material)| {
+                spawner.texture_pass(
+                    "aurora",
+                    aurora_material.0.clone(),
+                    aurora_layer,
+                    // before the default aurora camera
+                    (isize::MIN..aurora_settings.camera_order).rev(),
+                    sky_settings.render_target_format(),
+                    sky_settings.hdr,
+                )
+            });
+        if let Some(aurora) = &aurora {
+            sky_material.aurora_image = aurora.texture.clone();
+        }
+
+        let skybox = spawner.skybox(sky_materials.add(sky_material), sky_layer);
+
+        // the camera must see its own skybox, on top of what it already renders
+        let camera_layers = render_layers.cloned().unwrap_or_default().with(sky_layer);
+        commands.entity(*camera_entity).insert((
+            MagnetSkyRig {
+                slot,
+                skybox,
+                gradient_mesh: gradient.as_ref().map(|pass| pass.mesh),
+                gradient_camera: gradient.as_ref().map(|pass| pass.camera),
+                gradient_texture: gradient.map(|pass| pass.texture),
+                aurora_mesh: aurora.as_ref().map(|pass| pass.mesh),
+                aurora_camera: aurora.as_ref().map(|pass| pass.camera),
+                aurora_texture: aurora.map(|pass| pass.texture),
+            },
+            camera_layers,
+        ));
+    }
+}
+
+// the parts are despawned by despawn_orphan_magnet_rigs
+fn remove_magnet_rig(
+    commands: &mut Commands,
+    camera: Entity,
+    rig: &MagnetSkyRig,
+    render_layers: Option<&RenderLayers>,
+    sky_settings: &SkySettings,
+) {
+    let Ok(mut camera_commands) = commands.get_entity(camera) else {
+        return;
+    };
+    camera_commands.try_remove::<MagnetSkyRig>();
+    if let Some(render_layers) = render_layers {
+        let (sky_layer, _, _) = slot_layers(sky_settings, rig.slot);
+        camera_commands.try_insert(render_layers.clone().without(sky_layer));
+    }
+}
+
+// the SkyboxMagnetTag was removed, or the camera despawned
+fn despawn_orphan_magnet_rigs(
+    mut commands: Commands,
+    rig_parts: Query<(Entity, &MagnetRigOf)>,
+    rigs: Query<(
+        Entity,
+        &MagnetSkyRig,
+        Option<&RenderLayers>,
+        Has<SkyboxMagnetTag>,
+    )>,
+    sky_settings: Res<SkySettings>,
+) {
+    for (camera, rig, render_layers, is_magnet) in &rigs {
+        if !is_magnet {
+            remove_magnet_rig(&mut commands, camera, rig, render_layers, &sky_settings);
+        }
+    }
+    for (entity, owner) in &rig_parts {
+        let owned = rigs.get(owner.0).is_ok_and(|(_, rig, _, is_magnet)| {
+            is_magnet && rig.meshes().chain(rig.cameras()).any(|part| part == entity)
+        });
+        if !owned {
+            commands.entity(entity).try_despawn();
+        }
+    }
+}
+
+type DefaultSkyboxLayers<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, Option<&'static RenderLayers>),
+    (With<MeshMaterial3d<FullSkyMaterial>>, DefaultSkyFilter),
+>;
+type AnyRigFilter = Or<(With<MagnetSkyRig>, With<SkyInstanceRig>)>;
+type PrimaryMagnetCameras<'w, 's> =
+    Query<'w, 's, (Entity, Option<&'static RenderLayers>), (PrimaryMagnetFilter, With<Camera>)>;
+
+// on the default layer, every rigged or SkyInstance camera would see the default skybox
+// as a small cube around the primary camera. it moves to its own layer, that only the primary camera sees
+fn share_default_skybox_layer(
+    mut commands: Commands,
+    rigs: Query<(), AnyRigFilter>,
+    skyboxes: DefaultSkyboxLayers,
+    primary_cameras: PrimaryMagnetCameras,
+    sky_settings: Res<SkySettings>,
+) {
+    let layer = sky_settings.magnet_rig_render_layer;
+    let Ok((skybox, skybox_layers)) = skyboxes.single() else {
+        return;
+    };
+    match skybox_layers {
+        // rendered to a texture by SkyTexturePlugin, already on its own layer
+        Some(layers) if *layers != RenderLayers::layer(layer) => return,
+        Some(_) => {}
+        None if rigs.is_empty() => return,
+        None => {
+            commands.entity(skybox).insert(RenderLayers::layer(layer));
+        }
+    }
+    // the primary camera can change, when it goes away
+    for (camera, camera_layers) in &primary_cameras {
+        let camera_layers = camera_layers.cloned().unwrap_or_default();
+        if !camera_layers.intersects(&RenderLayers::layer(layer)) {
+            commands.entity(camera).insert(camera_layers.with(layer));
+        }
+    }
+}
+
+// a rig skybox is a copy of the default skybox material, with its own textures
+fn sync_magnet_rig_skyboxes(
+    mut events: MessageReader<AssetEvent<FullSkyMaterial>>,
+    default_skyboxes: Query<&MeshMaterial3d<FullSkyMaterial>, DefaultSkyFilter>,
+    rigs: Query<Ref<MagnetSkyRig>>,
+    rig_skyboxes: Query<&MeshMaterial3d<FullSkyMaterial>, With<MagnetRigOf>>,
+    mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
+) {
+    let Ok(default_skybox) = default_skyboxes.single() else {
+        events.clear();
+        return;
+    };
+    let default_changed = events.read().fold(false, |changed, event| {
+        changed || event.is_modified(default_skybox) || event.is_added(default_skybox)
+    });
+    let Some(default_material) = sky_materials.get(default_skybox).cloned() else {
+        return;
+    };
+    for rig in &rigs {
+        if !default_changed && !rig.is_added() {
+            continue;
+        }
+        let Ok(handle) = rig_skyboxes.get(rig.skybox) else {
+            continue;
+        };
+        let Some(sky_material) = sky_materials.get_mut(handle) else {
+            continue;
+        };
+        let gradient_image = rig
+            .gradient_texture
+            .clone()
+            .unwrap_or_else(|| default_material.gradient_image.clone());
+        let aurora_image = rig
+            .aurora_texture
+            .clone()
+            .unwrap_or_else(|| default_material.aurora_image.clone());
+        *sky_material = FullSkyMaterial {
+            gradient_image,
+            aurora_image,
+            ..default_material.clone()
+        };
+    }
+}
+
+// the gradient texture matches the camera's viewport, the aurora a percent of it
+fn resize_magnet_rigs(
+    rigs: Query<(&Camera, &MagnetSkyRig)>,
+    mut images: ResMut<Assets<Image>>,
+    aurora_settings: Option<Res<AuroraSettings>>,
+) {
+    for (camera, rig) in &rigs {
+        let Some(size) = camera.physical_viewport_size() else {
+            continue;
+        };
+        if let Some(gradient_texture) = &rig.gradient_texture {
+            utils::resize_render_target(&mut images, gradient_texture, size);
+        }
+        if let Some(aurora_texture) = &rig.aurora_texture
+            && let Some(aurora_settings) = &aurora_settings
+        {
+            let percent = aurora_settings.render_texture_percent.clamp(0.0, 1.0);
+            let size = (size.as_vec2() * percent).as_uvec2();
+            utils::resize_render_target(&mut images, aurora_texture, size);
+        }
+    }
+}
+
+fn magnet_rig_follow_camera(
+    rigs: Query<(&Transform, &Projection, &MagnetSkyRig)>,
+    mut rig_parts: Query<
+        (&mut Transform, Option<&mut Projection>),
+        (With<MagnetRigOf>, Without<MagnetSkyRig>),
+    >,
+) {
+    for (camera_transform, camera_projection, rig) in &rigs {
+        for mesh in rig.meshes() {
+            if let Ok((mut transform, _)) = rig_parts.get_mut(mesh) {
+                transform.translation = camera_transform.translation;
+            }
+        }
+        for camera in rig.cameras() {
+            if let Ok((mut transform, Some(mut projection))) = rig_parts.get_mut(camera) {
+                *transform = *camera_transform;
+                *projection = camera_projection.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::storage::ShaderStorageBuffer;
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_asset::<ShaderStorageBuffer>()
+            .init_asset::<FullSkyMaterial>()
+            .init_asset::<FullGradientMaterial>()
+            .init_asset::<AuroraMaterial>()
+            .insert_resource(SkySettings::default())
+            .add_plugins(MagnetRigPlugin);
+
+        // the default skybox and gradient, as spawned by SkyPlugin
+        let world = app.world_mut();
+        let sky_material = world
+            .resource_mut::<Assets<FullSkyMaterial>>()
+            .add(FullSkyMaterial::default());
+        let gradient_material = world
+            .resource_mut::<Assets<FullGradientMaterial>>()
+            .add(FullGradientMaterial::default());
+        world.spawn(MeshMaterial3d(sky_material));
+        world.spawn(MeshMaterial3d(gradient_material));
+        app
+    }
+
+    fn spawn_camera(app: &mut App) -> Entity {
+        app.world_mut()
+            .spawn((Camera3d::default(), SkyboxMagnetTag))
+            .id()
+    }
+
+    fn rig(app: &App, camera: Entity) -> Option<MagnetSkyRig> {
+        app.world().get::<MagnetSkyRig>(camera).cloned()
+    }
+
+    fn rig_parts(app: &mut App) -> usize {
+        app.world_mut()
+            .query::<&MagnetRigOf>()
+            .iter(app.world())
+            .count()
+    }
+
+    #[test]
+    fn extra_cameras_get_a_rig() {
+        let mut app = app();
+        let primary = spawn_camera(&mut app);
+        app.update();
+        assert!(rig(&app, primary).is_none());
+        assert_eq!(rig_parts(&mut app), 0);
+
+        let second = spawn_camera(&mut app);
+        let third = spawn_camera(&mut app);
+        app.update();
+        assert!(rig(&app, primary).is_none());
+        let (second_rig, third_rig) = (rig(&app, second).unwrap(), rig(&app, third).unwrap());
+        assert_ne!(second_rig.slot, third_rig.slot);
+        // skybox, gradient mesh and gradient camera, no aurora without AuroraSettings
+        assert_eq!(rig_parts(&mut app), 6);
+        assert!(second_rig.aurora_mesh.is_none());
+
+        // every rig camera sees its own skybox layer
+        let settings = SkySettings::default();
+        let (sky_layer, _, _) = slot_layers(&settings, second_rig.slot);
+        let layers = app.world().get::<RenderLayers>(second).unwrap();
+        assert!(layers.intersects(&RenderLayers::layer(sky_layer)));
+
+        // the rig gradient cameras render in a fixed order
+        let order = |rig: &MagnetSkyRig| {
+            let camera = rig.gradient_camera.unwrap();
+            app.world().get::<Camera>(camera).unwrap().order
+        };
+        assert_ne!(order(&second_rig), order(&third_rig));
+        assert_ne!(order(&second_rig), settings.camera_gradient_order);
+
+        // nothing changes on the next frames
+        app.update();
+        app.update();
+        assert_eq!(rig_parts(&mut app), 6);
+    }
+
+    #[test]
+    fn rig_cameras_skip_taken_orders() {
+        let mut app = app();
+        let order = SkySettings::default().camera_gradient_order + 1;
+        // like the gradient camera of a SkyInstance
+        app.world_mut().spawn(Camera { order, ..default() });
+        spawn_camera(&mut app);
+        let second = spawn_camera(&mut app);
+        app.update();
+        let gradient_camera = rig(&app, second).unwrap().gradient_camera.unwrap();
+        let rig_order = app.world().get::<Camera>(gradient_camera).unwrap().order;
+        assert_eq!(rig_order, order + 1);
+    }
+
+    #[test]
+    fn removing_the_tag_despawns_the_rig() {
+        let mut app = app();
+        spawn_camera(&mut app);
+        let second = spawn_camera(&mut app);
+        app.update();
+        let second_rig = rig(&app, second).unwrap();
+
+        app.world_mut()
+            .entity_mut(second)
+            .remove::<SkyboxMagnetTag>();
+        app.update();
+        assert!(rig(&app, second).is_none());
+        assert_eq!(rig_parts(&mut app), 0);
+        assert!(app.world().get_entity(second_rig.skybox).is_err());
+        // the camera no longer sees the rig skybox layer
+        let (sky_layer, _, _) = slot_layers(&SkySettings::default(), second_rig.slot);
+        let layers = app.world().get::<RenderLayers>(second).unwrap();
+        assert!(!layers.intersects(&RenderLayers::layer(sky_layer)));
+    }
+
+    #[test]
+    fn despawned_primary_hands_over_the_default_skybox() {
+        let mut app = app();
+        let primary = spawn_camera(&mut app);
+        let second = spawn_camera(&mut app);
+        app.update();
+        assert!(rig(&app, second).is_some());
+
+        app.world_mut().despawn(primary);
+        app.update();
+        app.update();
+        assert!(rig(&app, second).is_none());
+        assert_eq!(rig_parts(&mut app), 0);
+        // the default skybox moved to its own layer, the new primary camera sees it
+        let layer = SkySettings::default().magnet_rig_render_layer;
+        let layers = app.world().get::<RenderLayers>(second).unwrap();
+        assert!(layers.intersects(&RenderLayers::layer(layer)));
+    }
+}
@@ -40,6 +40,7 @@ impl Plugin for NoisePlugin {
 /// 128x128x128 = 2 mb
 /// 256x256x256 = 16 mb
 /// 514x514x514 = 129 mb
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Resource, Reflect, Clone)]
 pub struct NoiseSettings {
     /// size of 3d noise texture
@@ -157,6 +158,8 @@ pub fn update_noise_textures(
             }
             // else continue below, to generate the textures
         }
+        // an older load still in flight would overwrite the textures generated below
+        commands.remove_resource::<PendingNoiseTextureAsset>();
     }
 
     // generate and set noise texture
@@ -1,21 +1,21 @@
 use bevy::{
-    asset::RenderAssetUsages,
-    camera::visibility::RenderLayers,
-    image::ImageSampler,
-    prelude::*,
-    render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    camera::visibility::RenderLayers, mesh::MeshTag, prelude::*,
+    render::render_resource::TextureFormat,
 };
 
 use crate::{
     ambient_driver::AmbientDriverPlugin,
     aurora::AuroraPlugin,
-    bind_groups::{GradientBindGroup, StarsBindGroup},
+    bind_groups::StarsBindGroup,
     cycle::SkyCyclePlugin,
     gradient_driver::GradientDriverPlugin,
-    gradient_material::{FullGradientMaterial, GradientMaterialPlugin},
+    gradient_material::{self, FullGradientMaterial, GradientMaterialPlugin},
+    instance::{SkyInstance, SkyInstanceOf, SkyInstancePlugin},
+    magnet_rig::{MagnetRigOf, MagnetRigPlugin, MagnetSkyRig},
     noise::{NoiseHandles, NoisePlugin, NoiseSettings},
     presets::SkyPresetPlugin,
-    sky_material::FullSkyMaterial,
+    season::SeasonPlugin,
+    sky_material::{self, FullSkyMaterial},
     sky_texture::{SkyTexturePlugin, SkyTexturePluginSettings},
     sun::SunDriverPlugin,
     utils,
@@ -27,6 +27,27 @@ pub struct SkySettings {
     pub skybox_gradient_render_layer: RenderLayers,
     pub spawn_default_skybox: bool,
     pub stars_bind_group: StarsBindGroup,
+    /// render the gradient, aurora and full sky textures in HDR, so colors can go above 1.0.
+    /// needed by [`crate::gradient::GradientColor::Hdr`], your camera needs `Hdr` too
+    pub hdr: bool,
+    /// format of the [`GradientTextureHandle`] render target, None = [`Self::render_target_format`].
+    /// a float format like `Rgba16Float` keeps dark gradients from banding
+    pub gradient_texture_format: Option<TextureFormat>,
+    /// noise added to the final sky color to hide banding, in 8 bit color steps.
+    /// 0.0 = off, 1.0 is usually enough. the gradient texture itself isn't dithered
+    pub dither_strength: f32,
+    /// evaluate the gradient inside the sky shader, saving the gradient camera and its full screen pass.
+    /// the camera is still spawned if something samples [`GradientTextureHandle`],
+    /// see [`Self::keep_gradient_texture`]. only read at startup
+    pub inline_gradient: bool,
+    /// keep rendering [`GradientTextureHandle`] with [`Self::inline_gradient`],
+    /// set this if your own shaders sample it, like the fog example.
+    /// [`SkyTexturePlugin`] keeps it too. only read at startup
+    pub keep_gradient_texture: bool,
+    /// with more than one [`SkyboxMagnetTag`] camera, or any [`SkyInstance`] camera,
+    /// the default skybox moves to this layer, only seen by the first SkyboxMagnetTag camera.
+    /// the [`MagnetSkyRig`] of every extra camera takes the next 3 layers
+    pub magnet_rig_render_layer: usize,
 }
 
 impl Default for SkySettings {
@@ -36,10 +57,43 @@ impl Default for SkySettings {
             spawn_default_skybox: true,
             skybox_gradient_render_layer: RenderLayers::layer(6),
             stars_bind_group: StarsBindGroup::default(),
+            hdr: false,
+            gradient_texture_format: None,
+            dither_strength: 0.0,
+            inline_gradient: false,
+            keep_gradient_texture: false,
+            magnet_rig_render_layer: 16,
         }
     }
 }
 
+impl SkySettings {
+    /// the format of the gradient and aurora render targets
+    pub fn render_target_format(&self) -> TextureFormat {
+        if self.hdr {
+            TextureFormat::Rgba16Float
+        } else {
+            TextureFormat::Bgra8UnormSrgb
+        }
+    }
+
+    /// the format of the gradient render target
+    pub fn gradient_texture_format(&self) -> TextureFormat {
+        self.gradient_texture_format
+            .unwrap_or_else(|| self.render_target_format())
+    }
+
+    /// false if nothing needs the [`GradientTextureHandle`] texture, and the gradient camera can be skipped
+    pub fn renders_gradient_texture(&self, sky_texture_plugin: bool) -> bool {
+        !self.inline_gradient || self.keep_gradient_texture || sky_texture_plugin
+    }
+
+    /// a camera without `Hdr` renders in 8 bit, even into a float texture
+    pub fn gradient_camera_hdr(&self) -> bool {
+        utils::is_float_format(self.gradient_texture_format())
+    }
+}
+
 /// controlls what features you want.  
 /// you might not want to use the default Cycle/SunDriver/GradientDriver/Aurora for example
 /// then you can skip that plugin and implement your own.
@@ -52,6 +106,8 @@ pub struct SkyPluginBuilder {
     pub noise: NoisePlugin,
     pub aurora: Option<AuroraPlugin>,
     pub cycle: Option<SkyCyclePlugin>,
+    /// opt in, modulates the cycle timings over a year
+    pub season: Option<SeasonPlugin>,
     pub sun_driver: Option<SunDriverPlugin>,
     pub gradient_driver: Option<GradientDriverPlugin>,
     pub ambient_driver: Option<AmbientDriverPlugin>,
@@ -70,6 +126,7 @@ impl SkyPluginBuilder {
             noise: NoisePlugin::default(),
             aurora: None,
             cycle: None,
+            season: None,
             sun_driver: None,
             gradient_driver: None,
             use_preset_plugin: false,
@@ -84,6 +141,7 @@ impl SkyPluginBuilder {
             noise: NoisePlugin::default(),
             aurora: Some(AuroraPlugin::default()),
             cycle: Some(SkyCyclePlugin::default()),
+            season: None,
             sun_driver: Some(SunDriverPlugin::default()),
             gradient_driver: Some(GradientDriverPlugin::default()),
             use_preset_plugin: true,
@@ -107,6 +165,36 @@ impl SkyPluginBuilder {
         self
     }
 
+    /// see [`SkySettings::hdr`]
+    pub fn with_hdr(mut self) -> Self {
+        self.settings.hdr = true;
+        self
+    }
+
+    /// see [`SkySettings::gradient_texture_format`]
+    pub fn with_gradient_texture_format(mut self, format: TextureFormat) -> Self {
+        self.settings.gradient_texture_format = Some(format);
+        self
+    }
+
+    /// see [`SkySettings::dither_strength`]
+    pub fn with_dither(mut self, strength: f32) -> Self {
+        self.settings.dither_strength = strength;
+        self
+    }
+
+    /// see [`SkySettings::inline_gradient`]
+    pub fn with_inline_gradient(mut self) -> Self {
+        self.settings.inline_gradient = true;
+        self
+    }
+
+    /// see [`SkySettings::keep_gradient_texture`]
+    pub fn with_keep_gradient_texture(mut self) -> Self {
+        self.settings.keep_gradient_texture = true;
+        self
+    }
+
     pub fn build(self) -> SkyPlugin {
         SkyPlugin { sky_builder: self }
     }
@@ -131,6 +219,11 @@ impl SkyPluginBuilder {
         self
     }
 
+    pub fn set_season(mut self, season: SeasonPlugin) -> Self {
+        self.season = Some(season);
+        self
+    }
+
     pub fn set_aurora(mut self, aurora_plugin: AuroraPlugin) -> Self {
         self.aurora = Some(aurora_plugin);
         self
@@ -167,6 +260,10 @@ impl Plugin for SkyPlugin {
         app.add_plugins(self.sky_builder.noise.clone());
         app.add_plugins(SkyPresetPlugin);
         app.add_plugins(GradientMaterialPlugin);
+        app.add_plugins(SkyInstancePlugin);
+        app.add_plugins(MagnetRigPlugin);
+        #[cfg(feature = "serde")]
+        app.add_plugins(crate::gradient_assets::GradientAssetsPlugin);
 
         if self.sky_builder.render_sky_to_texture {
             app.add_plugins(SkyTexturePlugin::default());
@@ -178,6 +275,12 @@ impl Plugin for SkyPlugin {
         if let Some(cycle_plugin) = &self.sky_builder.cycle {
             app.add_plugins(cycle_plugin.clone());
         }
+        if let Some(season_plugin) = &self.sky_builder.season {
+            if self.sky_builder.cycle.is_none() {
+                error!("season plugin requires cycle plugin. prepare for crash");
+            }
+            app.add_plugins(season_plugin.clone());
+        }
         if let Some(sun_driver) = &self.sky_builder.sun_driver {
             if self.sky_builder.cycle.is_none() {
                 error!("sun driver requires cycle plugin. prepare for crash");
@@ -215,6 +318,14 @@ impl Plugin for SkyPlugin {
             PostUpdate,
             (sky_follow_camera, gradient_follow_camera).before(TransformSystems::Propagate),
         );
+        app.add_systems(PostUpdate, drive_dither_strength);
+        app.add_systems(PostUpdate, sky_material::init_inline_gradient_buffers);
+        if self.sky_builder.settings.inline_gradient {
+            app.add_systems(
+                PostUpdate,
+                sky_material::sync_inline_gradient.after(gradient_material::upload_gradient_stops),
+            );
+        }
     }
 }
 
@@ -226,10 +337,21 @@ impl Default for SkyPlugin {
     }
 }
 
-/// attach to your main camera for the skybox to auto move to
+/// attach to your main camera for the skybox to auto move to.
+/// attach to every camera of a split screen, the extra cameras get their own [`MagnetSkyRig`]
 #[derive(Component)]
 pub struct SkyboxMagnetTag;
 
+/// the magnet camera using the default skybox, gradient and aurora
+pub(crate) type PrimaryMagnetFilter = (
+    With<SkyboxMagnetTag>,
+    Without<MagnetSkyRig>,
+    Without<SkyInstance>,
+);
+
+/// the default sky, ignores the rigs of SkyInstance and extra SkyboxMagnetTag cameras
+pub(crate) type DefaultSkyFilter = (Without<SkyInstanceOf>, Without<MagnetRigOf>);
+
 fn spawn_default_skybox(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -251,6 +373,8 @@ fn spawn_default_skybox(
             stars: sky_settings.stars_bind_group.clone(),
             ..default()
         })),
+        // the cycle percent, used by an inline GradientDriverMode::BakedLut
+        MeshTag::default(),
     ));
     if let Some(settings) = sky_texture_plugin_settings {
         skybox_commands.insert(settings.sky_render_layer.clone());
@@ -264,30 +388,40 @@ fn spawn_default_skybox_gradient(
     mut meshes: ResMut<Assets<Mesh>>,
     mut gradient_materials: ResMut<Assets<FullGradientMaterial>>,
     gradient_texture_handle: Res<GradientTextureHandle>,
+    sky_texture_plugin_settings: Option<Res<SkyTexturePluginSettings>>,
     sky_settings: Res<SkySettings>,
 ) {
-    commands.spawn((
+    let mut gradient_commands = commands.spawn((
         Name::new("sky_skybox_full_gradient"),
-        Mesh3d(meshes.add(utils::default_sky_mesh())),
-        MeshMaterial3d(gradient_materials.add(FullGradientMaterial {
-            gradient_bind_group: GradientBindGroup::default(),
-        })),
-        sky_settings.skybox_gradient_render_layer.clone(),
-    ));
-
-    commands.spawn((
-        Name::new("camera_gradient"),
-        Camera3d::default(),
-        GradientCameraTag,
-        Camera {
-            order: sky_settings.camera_gradient_order,
-            target: gradient_texture_handle.render_target.clone().into(),
-            clear_color: ClearColorConfig::Custom(Color::NONE),
-            ..default()
-        },
-        Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)).looking_at(Vec3::ZERO, Vec3::Y),
+        MeshMaterial3d(gradient_materials.add(FullGradientMaterial::default())),
+        // the cycle percent, used by GradientDriverMode::BakedLut
+        MeshTag::default(),
         sky_settings.skybox_gradient_render_layer.clone(),
     ));
+    // inlined and nothing samples the texture,
+    // the material is only the source of the skybox gradient, nothing renders it
+    if !sky_settings.renders_gradient_texture(sky_texture_plugin_settings.is_some()) {
+        return;
+    }
+    gradient_commands.insert(Mesh3d(meshes.add(utils::default_sky_mesh())));
+
+    commands
+        .spawn((
+            Name::new("camera_gradient"),
+            Camera3d::default(),
+            GradientCameraTag,
+            Camera {
+                order: sky_settings.camera_gradient_order,
+                target: gradient_texture_handle.render_target.clone().into(),
+                clear_color: ClearColorConfig::Custom(Color::NONE),
+                ..default()
+            },
+            Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)).looking_at(Vec3::ZERO, Vec3::Y),
+            sky_settings.skybox_gradient_render_layer.clone(),
+        ))
+        .insert_if(utils::hdr_render_target_camera(), || {
+            sky_settings.gradient_camera_hdr()
+        });
 }
 
 // aurora texture is defined by sky, and the aurora render into it. it needs to be defined by the sky plugin
@@ -300,26 +434,11 @@ pub struct AuroraTextureHandle {
 pub fn spawn_aurora_texture(
     mut images: ResMut<Assets<Image>>,
     mut aurora_texture_handle: ResMut<AuroraTextureHandle>,
+    sky_settings: Res<SkySettings>,
 ) {
-    let size = Extent3d {
-        width: 2,
-        height: 2,
-        ..default()
-    };
-
-    let mut aurora_image = Image::new_fill(
-        size,
-        TextureDimension::D2,
-        &[0, 0, 0, 0],
-        TextureFormat::Bgra8UnormSrgb,
-        RenderAssetUsages::default(),
-    );
-    aurora_image.sampler = ImageSampler::linear();
-    aurora_image.texture_descriptor.usage =
-        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
-
-    let aurora_image_handle = images.add(aurora_image);
-    aurora_texture_handle.render_target = aurora_image_handle;
+    aurora_texture_handle.render_target = images.add(utils::render_target_image(
+        sky_settings.render_target_format(),
+    ));
 }
 
 #[derive(Resource)]
@@ -329,64 +448,41 @@ pub struct GradientTextureHandle {
 
 pub fn spawn_gradient_texture(
     mut images: ResMut<Assets<Image>>,
-    mut aurora_texture_handle: ResMut<GradientTextureHandle>,
+    mut gradient_texture_handle: ResMut<GradientTextureHandle>,
+    sky_settings: Res<SkySettings>,
 ) {
-    let size = Extent3d {
-        width: 2,
-        height: 2,
-        ..default()
-    };
-
-    let mut aurora_image = Image::new_fill(
-        size,
-        TextureDimension::D2,
-        &[0, 0, 0, 0],
-        TextureFormat::Bgra8UnormSrgb,
-        RenderAssetUsages::default(),
-    );
-    aurora_image.sampler = ImageSampler::linear();
-    aurora_image.texture_descriptor.usage =
-        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
-
-    let aurora_image_handle = images.add(aurora_image);
-    aurora_texture_handle.render_target = aurora_image_handle;
+    gradient_texture_handle.render_target = images.add(utils::render_target_image(
+        sky_settings.gradient_texture_format(),
+    ));
 }
 
+// SkyInstance and MagnetSkyRig rigs follow their own camera
+type DefaultSkyMeshFilter<M> = (Without<Camera>, With<MeshMaterial3d<M>>, DefaultSkyFilter);
+
 fn sky_follow_camera(
-    camera_query: Query<&Transform, (With<SkyboxMagnetTag>, With<Camera>)>,
-    mut sky_query: Query<&mut Transform, (Without<Camera>, With<MeshMaterial3d<FullSkyMaterial>>)>,
+    camera_query: Query<&Transform, (PrimaryMagnetFilter, With<Camera>)>,
+    mut sky_query: Query<&mut Transform, DefaultSkyMeshFilter<FullSkyMaterial>>,
     mut warned_once: Local<bool>,
 ) {
-    let mut count = 0;
-    for main_cam_transform in camera_query.iter() {
-        count += 1;
-        for mut sky_transform in &mut sky_query {
-            sky_transform.translation = main_cam_transform.translation;
-        }
-    }
-    if count == 0 {
+    let Some(main_cam_transform) = camera_query.iter().next() else {
         if !*warned_once {
             warn!("SkyPlugin: no camera with SkyBoxMagnetTag to transform to");
             *warned_once = true;
         }
-    } else if count > 1 {
-        if !*warned_once {
-            warn!("SkyPlugin: MORE THAN 1 CAMERA WITH SkyBoxMagnetTag");
-            *warned_once = true;
-        }
+        return;
+    };
+    for mut sky_transform in &mut sky_query {
+        sky_transform.translation = main_cam_transform.translation;
     }
 }
 
 fn gradient_follow_camera(
     primary_cameras: Query<
         (&Transform, &Camera, &Projection),
-        (Without<GradientCameraTag>, With<SkyboxMagnetTag>),
+        (Without<GradientCameraTag>, PrimaryMagnetFilter),
     >,
     mut gradient_camera: Query<(&mut Transform, &Camera, &mut Projection), With<GradientCameraTag>>,
-    mut gradient_mesh: Query<
-        &mut Transform,
-        (Without<Camera>, With<MeshMaterial3d<FullGradientMaterial>>),
-    >,
+    mut gradient_mesh: Query<&mut Transform, DefaultSkyMeshFilter<FullGradientMaterial>>,
 ) {
     if let Some((cam_tf, _camera, cam_proj)) = primary_cameras.iter().next() {
         for (mut aurora_tf, _cam, mut aurora_projection) in gradient_camera.iter_mut() {
@@ -399,3 +495,65 @@ fn gradient_follow_camera(
         }
     }
 }
+
+// every sky material dithers with the strength from SkySettings,
+// only the final color, dithering the gradient too would add the noise twice
+fn drive_dither_strength(
+    sky_settings: Res<SkySettings>,
+    mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
+) {
+    let strength = sky_settings.dither_strength;
+    // read only first, get_mut marks the material as modified
+    let sky_ids: Vec<_> = sky_materials
+        .iter()
+        .filter(|(_, material)| material.dither_strength != strength)
+        .map(|(id, _)| id)
+        .collect();
+    for id in sky_ids {
+        if let Some(material) = sky_materials.get_mut(id) {
+            material.dither_strength = strength;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient_cameras(sky_settings: SkySettings, sky_texture_plugin: bool) -> usize {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<FullGradientMaterial>()
+            .insert_resource(sky_settings)
+            .insert_resource(GradientTextureHandle {
+                render_target: Handle::default(),
+            })
+            .add_systems(Startup, spawn_default_skybox_gradient);
+        if sky_texture_plugin {
+            app.insert_resource(SkyTexturePluginSettings::default());
+        }
+        app.update();
+        app.world_mut()
+            .query_filtered::<(), With<GradientCameraTag>>()
+            .iter(app.world())
+            .count()
+    }
+
+    #[test]
+    fn inline_gradient_skips_the_camera_without_texture_consumers() {
+        let inline = SkySettings {
+            inline_gradient: true,
+            ..default()
+        };
+        assert_eq!(gradient_cameras(SkySettings::default(), false), 1);
+        assert_eq!(gradient_cameras(inline.clone(), false), 0);
+        // SkyTexturePlugin or the user still sample GradientTextureHandle
+        assert_eq!(gradient_cameras(inline.clone(), true), 1);
+        let kept = SkySettings {
+            keep_gradient_texture: true,
+            ..inline
+        };
+        assert_eq!(gradient_cameras(kept, false), 1);
+    }
+}
@@ -2,60 +2,176 @@ use bevy::prelude::*;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "serde")]
+use crate::gradient::SkyGradientBuilderFile;
 use crate::{
     aurora_material::AuroraMaterial,
-    gradient::{GradientBuilder, SkyGradientBuilder},
+    gradient::{
+        GradientBuilder, GradientColor, GroundGradientBuilder, SkyGradientBuilder, SkyGradientStop,
+    },
     gradient_material::FullGradientMaterial,
+    magnet_rig::MagnetRigOf,
+    plugin::DefaultSkyFilter,
     sky_material::FullSkyMaterial,
     sun::SunSettings,
 };
 
-pub const DEFAULT_SKY_COLORS_BUILDER: SkyGradientBuilder = SkyGradientBuilder {
-    gradient_builder_stop0: GradientBuilder {
-        sunset_color: [255, 70, 70, 255],
-        sunrise_color: [255, 70, 70, 255],
-        day_low_color: [157, 157, 248, 255],
-        day_high_color: [48, 48, 255, 255],
-        night_low_color: [0, 3, 40, 255],
-        night_high_color: [0, 0, 45, 255],
+pub const DEFAULT_SKY_GRADIENT_STOPS: [SkyGradientStop; 4] = [
+    SkyGradientStop {
+        position: 0.38,
+        colors: GradientBuilder {
+            sunset_color: GradientColor::Srgb([255, 70, 70, 255]),
+            sunrise_color: GradientColor::Srgb([255, 70, 70, 255]),
+            day_low_color: GradientColor::Srgb([157, 157, 248, 255]),
+            day_high_color: GradientColor::Srgb([48, 48, 255, 255]),
+            night_low_color: GradientColor::Srgb([0, 3, 40, 255]),
+            night_high_color: GradientColor::Srgb([0, 0, 45, 255]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-    gradient_builder_stop1: GradientBuilder {
-        sunset_color: [243, 84, 47, 255],
-        sunrise_color: [243, 84, 47, 255],
-        day_low_color: [205, 242, 255, 255],
-        day_high_color: [0, 226, 255, 255],
-        night_low_color: [47, 0, 93, 255],
-        night_high_color: [0, 32, 93, 255],
+    SkyGradientStop {
+        position: 0.47,
+        colors: GradientBuilder {
+            sunset_color: GradientColor::Srgb([243, 84, 47, 255]),
+            sunrise_color: GradientColor::Srgb([243, 84, 47, 255]),
+            day_low_color: GradientColor::Srgb([205, 242, 255, 255]),
+            day_high_color: GradientColor::Srgb([0, 226, 255, 255]),
+            night_low_color: GradientColor::Srgb([47, 0, 93, 255]),
+            night_high_color: GradientColor::Srgb([0, 32, 93, 255]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-    gradient_builder_stop2: GradientBuilder {
-        sunset_color: [255, 242, 72, 255],
-        sunrise_color: [255, 242, 72, 255],
-        day_low_color: [182, 200, 254, 255],
-        day_high_color: [0, 170, 255, 255],
-        night_low_color: [0, 38, 97, 255],
-        night_high_color: [0, 0, 112, 255],
+    SkyGradientStop {
+        position: 0.61,
+        colors: GradientBuilder {
+            sunset_color: GradientColor::Srgb([255, 242, 72, 255]),
+            sunrise_color: GradientColor::Srgb([255, 242, 72, 255]),
+            day_low_color: GradientColor::Srgb([182, 200, 254, 255]),
+            day_high_color: GradientColor::Srgb([0, 170, 255, 255]),
+            night_low_color: GradientColor::Srgb([0, 38, 97, 255]),
+            night_high_color: GradientColor::Srgb([0, 0, 112, 255]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-    gradient_builder_stop3: GradientBuilder {
-        sunset_color: [73, 177, 250, 255],
-        sunrise_color: [73, 177, 250, 255],
-        day_low_color: [224, 224, 255, 255],
-        day_high_color: [66, 195, 255, 255],
-        night_low_color: [74, 0, 89, 255],
-        night_high_color: [0, 0, 43, 255],
+    SkyGradientStop {
+        position: 1.0,
+        colors: GradientBuilder {
+            sunset_color: GradientColor::Srgb([73, 177, 250, 255]),
+            sunrise_color: GradientColor::Srgb([73, 177, 250, 255]),
+            day_low_color: GradientColor::Srgb([224, 224, 255, 255]),
+            day_high_color: GradientColor::Srgb([66, 195, 255, 255]),
+            night_low_color: GradientColor::Srgb([74, 0, 89, 255]),
+            night_high_color: GradientColor::Srgb([0, 0, 43, 255]),
+        },
+        keyframes: None,
+        animated_position: None,
     },
-};
+];
+
+/// colors below the horizon, used by [`crate::gradient::GroundGradientBuilder`]
+pub const DEFAULT_GROUND_GRADIENT_STOPS: [SkyGradientStop; 2] = [
+    SkyGradientStop {
+        position: 0.0,
+        colors: GradientBuilder {
+            sunrise_color: GradientColor::Srgb([61, 40, 38, 255]),
+            day_low_color: GradientColor::Srgb([58, 68, 50, 255]),
+            day_high_color: GradientColor::Srgb([70, 82, 58, 255]),
+            sunset_color: GradientColor::Srgb([61, 40, 38, 255]),
+            night_low_color: GradientColor::Srgb([6, 6, 12, 255]),
+            night_high_color: GradientColor::Srgb([3, 3, 8, 255]),
+        },
+        keyframes: None,
+        animated_position: None,
+    },
+    SkyGradientStop {
+        position: 1.0,
+        colors: GradientBuilder {
+            sunrise_color: GradientColor::Srgb([196, 112, 92, 255]),
+            day_low_color: GradientColor::Srgb([150, 160, 172, 255]),
+            day_high_color: GradientColor::Srgb([160, 172, 186, 255]),
+            sunset_color: GradientColor::Srgb([196, 112, 92, 255]),
+            night_low_color: GradientColor::Srgb([22, 20, 40, 255]),
+            night_high_color: GradientColor::Srgb([10, 10, 26, 255]),
+        },
+        keyframes: None,
+        animated_position: None,
+    },
+];
 
 /// data that controlls the look of a sky
 /// (not aurora upsampling size, nor noise 3dTexture, performance and "look" should be seperate)
 /// (None) values will not override current sky settings.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Default)]
+#[cfg_attr(feature = "serde", serde(from = "SkyPresetFile"))]
+#[derive(Default, Clone)]
 pub struct SkyPreset {
     pub gradient_bind_group: Option<crate::bind_groups::GradientBindGroup>,
     pub aurora_settings: Option<crate::bind_groups::AuroraBindGroup>,
     pub sun_settings: Option<SunSettings>,
     pub sky_colors_builder: Option<SkyGradientBuilder>,
     pub stars: Option<crate::bind_groups::StarsBindGroup>,
+    pub ground_colors_builder: Option<GroundGradientBuilder>,
+}
+
+/// SkyPreset as stored in files.
+/// legacy sky colors are positioned by the gradient_bind_group of the same preset
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct SkyPresetFile {
+    gradient_bind_group: Option<crate::bind_groups::GradientBindGroup>,
+    aurora_settings: Option<crate::bind_groups::AuroraBindGroup>,
+    sun_settings: Option<SunSettings>,
+    sky_colors_builder: Option<SkyGradientBuilderFile>,
+    stars: Option<crate::bind_groups::StarsBindGroup>,
+    ground_colors_builder: Option<GroundGradientBuilder>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SkyPresetFile> for SkyPreset {
+    fn from(file: SkyPresetFile) -> Self {
+        let legacy_positions = file
+            .gradient_bind_group
+            .as_ref()
+            .map(|bind_group| bind_group.positions.as_slice());
+        Self {
+            sky_colors_builder: file
+                .sky_colors_builder
+                .map(|builder| builder.into_builder(legacy_positions)),
+            gradient_bind_group: file.gradient_bind_group,
+            aurora_settings: file.aurora_settings,
+            sun_settings: file.sun_settings,
+            stars: file.stars,
+            ground_colors_builder: file.ground_colors_builder,
+        }
+    }
+}
+
+impl SkyPreset {
+    /// the current look of the default sky.
+    /// values belonging to disabled plugins are None
+    pub fn capture(world: &World) -> Self {
+        Self {
+            gradient_bind_group: default_sky_material::<FullGradientMaterial>(world)
+                .map(|material| material.gradient_bind_group.clone()),
+            aurora_settings: default_sky_material::<AuroraMaterial>(world)
+                .map(|material| material.aurora_settings.clone()),
+            sun_settings: world.get_resource::<SunSettings>().cloned(),
+            sky_colors_builder: world.get_resource::<SkyGradientBuilder>().cloned(),
+            stars: default_sky_material::<FullSkyMaterial>(world)
+                .map(|material| material.stars.clone()),
+            ground_colors_builder: world.get_resource::<GroundGradientBuilder>().cloned(),
+        }
+    }
+}
+
+// the material of the default sky, SkyInstance and MagnetSkyRig materials are ignored
+fn default_sky_material<M: Material>(world: &World) -> Option<&M> {
+    let mut query = world.try_query_filtered::<&MeshMaterial3d<M>, DefaultSkyFilter>()?;
+    let handle = query.single(world).ok()?;
+    world.get_resource::<Assets<M>>()?.get(handle)
 }
 
 pub struct SkyPresetPlugin;
@@ -74,13 +190,14 @@ pub struct ApplyPresetEvent {
 
 pub fn handle_apply_preset_events(
     mut events: MessageReader<ApplyPresetEvent>,
-    skyboxes: Query<&mut MeshMaterial3d<FullSkyMaterial>>,
-    auroras: Query<&mut MeshMaterial3d<AuroraMaterial>>,
-    gradient_handles: Query<&mut MeshMaterial3d<FullGradientMaterial>>,
+    skyboxes: Query<&mut MeshMaterial3d<FullSkyMaterial>, DefaultSkyFilter>,
+    auroras: Query<&mut MeshMaterial3d<AuroraMaterial>, Without<MagnetRigOf>>,
+    gradient_handles: Query<&mut MeshMaterial3d<FullGradientMaterial>, DefaultSkyFilter>,
     mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
     mut auroras_materials: ResMut<Assets<AuroraMaterial>>,
     mut gradient_materials: ResMut<Assets<FullGradientMaterial>>,
     mut sky_colors_builder_optional: Option<ResMut<SkyGradientBuilder>>,
+    mut ground_colors_builder_optional: Option<ResMut<GroundGradientBuilder>>,
     mut sun_settings_optional: Option<ResMut<SunSettings>>,
 ) {
     for event in events.read() {
@@ -94,6 +211,11 @@ pub fn handle_apply_preset_events(
                 **current_sky_colors_builder = new_sky_colors_builder.clone();
             }
         }
+        if let Some(new_ground_colors_builder) = &event.sky_preset.ground_colors_builder
+            && let Some(current_ground_colors_builder) = ground_colors_builder_optional.as_mut()
+        {
+            **current_ground_colors_builder = new_ground_colors_builder.clone();
+        }
 
         if let Some(star_settings) = &event.sky_preset.stars {
             let skybox_material_handle = skyboxes
@@ -124,3 +246,39 @@ pub fn handle_apply_preset_events(
         }
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    const PURPLE: &str = include_str!("../assets/sky_presets/purple.ron");
+
+    fn stop_positions(preset: &SkyPreset) -> Vec<f32> {
+        let builder = preset.sky_colors_builder.as_ref().unwrap();
+        builder.stops.iter().map(|stop| stop.position).collect()
+    }
+
+    #[test]
+    fn legacy_stops_use_saved_positions() {
+        let preset: SkyPreset = ron::de::from_str(PURPLE).unwrap();
+        assert_eq!(stop_positions(&preset), vec![0.38, 0.47, 0.61, 1.0]);
+
+        let moved = PURPLE.replace(
+            "positions: (0.38, 0.47, 0.61, 1.0)",
+            "positions: (0.1, 0.2, 0.3, 0.9)",
+        );
+        assert_ne!(moved, PURPLE);
+        let preset: SkyPreset = ron::de::from_str(&moved).unwrap();
+        assert_eq!(stop_positions(&preset), vec![0.1, 0.2, 0.3, 0.9]);
+    }
+
+    #[test]
+    fn legacy_stops_without_bind_group_use_default_positions() {
+        let start = PURPLE.find("gradient_bind_group").unwrap();
+        let end = PURPLE.find("aurora_settings").unwrap();
+        let without = format!("{}{}", &PURPLE[..start], &PURPLE[end..]);
+        let preset: SkyPreset = ron::de::from_str(&without).unwrap();
+        assert!(preset.gradient_bind_group.is_none());
+        assert_eq!(stop_positions(&preset), vec![0.38, 0.47, 0.61, 1.0]);
+    }
+}
@@ -0,0 +1,281 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ambient_driver::update_ambient_colors_builder,
+    cycle::{SkyTime, SkyTimeSettings, update_sky_time},
+    gradient::SkyGradientBuilder,
+    gradient_driver::{update_ground_colors_builder, update_sky_colors_builder},
+    sun::SunPath,
+    time_source::apply_sky_time_authority,
+};
+
+/// introduce a year on top of the sky cycle.
+/// every day the SkyTimeSettings are blended between summer and winter timings,
+/// so days are long in summer, and short in winter. REQUIRES CyclePlugin.
+///
+/// while this plugin is active, [`SeasonSettings`] owns `SkyTimeSettings`,
+/// edits to `SkyTimeSettings` are overwritten, change the summer and winter timings instead.
+#[derive(Clone, Default)]
+pub struct SeasonPlugin {
+    pub season_settings: SeasonSettings,
+}
+
+impl Plugin for SeasonPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SeasonSettings>();
+        app.insert_resource(self.season_settings.clone());
+        // after the day is ticked, and before the gradients are rebuilt from the new timings,
+        // so a new day gets its timings and colors in the same frame
+        app.add_systems(
+            Update,
+            drive_seasons
+                .after(update_sky_time)
+                .after(apply_sky_time_authority)
+                .before(update_sky_colors_builder)
+                .before(update_ground_colors_builder)
+                .before(update_ambient_colors_builder),
+        );
+    }
+}
+
+/// the sky timings and colors at the peak of summer and winter
+/// spring and autumn are a blend of the two
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Reflect, Clone)]
+pub struct SeasonSettings {
+    /// how many sky days (full cycles) in one year
+    pub days_per_year: u32,
+    /// which day of the year the first sky day (SkyTime.day = 0) is
+    pub start_day: u32,
+    /// 0.0 -> 1.0, when in the year summer peaks. winter peaks half a year later
+    pub summer_peak: f32,
+    /// timings at the peak of summer
+    pub summer_time_settings: SkyTimeSettings,
+    /// timings at the peak of winter
+    pub winter_time_settings: SkyTimeSettings,
+    /// if set, colors at the peak of summer.
+    /// both summer and winter colors must be set to blend colors
+    #[reflect(ignore)]
+    pub summer_colors: Option<SkyGradientBuilder>,
+    /// if set, colors at the peak of winter
+    #[reflect(ignore)]
+    pub winter_colors: Option<SkyGradientBuilder>,
+}
+
+impl Default for SeasonSettings {
+    fn default() -> Self {
+        Self {
+            days_per_year: 12,
+            start_day: 0,
+            // late june
+            summer_peak: 0.47,
+            summer_time_settings: SkyTimeSettings {
+                day_time_sec: 25.0,
+                night_time_sec: 15.0,
+                sunrise_time_sec: 3.0,
+                sunset_time_sec: 3.0,
+            },
+            winter_time_settings: SkyTimeSettings {
+                day_time_sec: 10.0,
+                night_time_sec: 30.0,
+                sunrise_time_sec: 1.5,
+                sunset_time_sec: 1.5,
+            },
+            summer_colors: None,
+            winter_colors: None,
+        }
+    }
+}
+
+impl SeasonSettings {
+    /// 0.0 -> 1.0, how far into the current year the day is
+    #[inline]
+    pub fn year_percent(&self, day: u32) -> f32 {
+        let days_per_year = self.days_per_year.max(1);
+        ((day + self.start_day) % days_per_year) as f32 / days_per_year as f32
+    }
+
+    /// 0: peak of winter
+    /// 1: peak of summer
+    #[inline]
+    pub fn summer_factor(&self, day: u32) -> f32 {
+        let distance = self.year_percent(day) - self.summer_peak;
+        0.5 + 0.5 * (distance * 2.0 * PI).cos()
+    }
+
+    /// the sky timings for a specific day
+    pub fn time_settings(&self, day: u32) -> SkyTimeSettings {
+        let t = self.summer_factor(day);
+        let w = &self.winter_time_settings;
+        let s = &self.summer_time_settings;
+        SkyTimeSettings {
+            day_time_sec: w.day_time_sec.lerp(s.day_time_sec, t),
+            night_time_sec: w.night_time_sec.lerp(s.night_time_sec, t),
+            sunrise_time_sec: w.sunrise_time_sec.lerp(s.sunrise_time_sec, t),
+            sunset_time_sec: w.sunset_time_sec.lerp(s.sunset_time_sec, t),
+        }
+    }
+
+    /// the sky colors for a specific day, None if summer or winter colors are missing
+    pub fn colors(&self, day: u32) -> Option<SkyGradientBuilder> {
+        let (Some(winter), Some(summer)) = (&self.winter_colors, &self.summer_colors) else {
+            return None;
+        };
+        Some(winter.lerp(summer, self.summer_factor(day)))
+    }
+}
+
+/// once per day, push the seasonal timings and colors into SkyTimeSettings and SkyGradientBuilder.
+/// GradientDriverPlugin and AmbientDriverPlugin picks up the changes and rebuilds their gradients.
+/// SkyTimeSettings edited by anything else are overwritten right away, with a warning
+fn drive_seasons(
+    sky_time: Res<SkyTime>,
+    season_settings: Res<SeasonSettings>,
+    mut sky_time_settings: ResMut<SkyTimeSettings>,
+    sky_colors_builder: Option<ResMut<SkyGradientBuilder>>,
+    sun_path: Option<ResMut<SunPath>>,
+    mut last_day: Local<Option<u32>>,
+    mut last_time_settings: Local<Option<SkyTimeSettings>>,
+) {
+    let edited = last_time_settings
+        .as_ref()
+        .is_some_and(|last| *last != *sky_time_settings);
+    if edited {
+        warn!(
+            "SkyPlugin: SkyTimeSettings is driven by SeasonSettings, edit SeasonSettings instead"
+        );
+    }
+    if *last_day == Some(sky_time.day) && !season_settings.is_changed() && !edited {
+        return;
+    }
+    *last_day = Some(sky_time.day);
+
+    // validate here, the PreUpdate validation has already run this frame
+    let mut time_settings = season_settings.time_settings(sky_time.day);
+    if let Err(err) = time_settings.validate() {
+        warn!("SkyPlugin: invalid SeasonSettings timings, clamping. reason: {err}");
+        time_settings = time_settings.clamped();
+    }
+    sky_time_settings.set_if_neq(time_settings.clone());
+    *last_time_settings = Some(time_settings);
+
+    if let (Some(colors), Some(mut sky_colors_builder)) =
+        (season_settings.colors(sky_time.day), sky_colors_builder)
+    {
+        *sky_colors_builder = colors;
+    }
+
+    // keep the astronomical sun in sync with the season
+    if let Some(mut sun_path) = sun_path
+        && let SunPath::Astronomical(astronomical) = sun_path.as_mut()
+    {
+        astronomical.day_of_year = season_settings.year_percent(sky_time.day) * 365.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sun::AstronomicalSunSettings;
+
+    use super::*;
+
+    // day 0 is the peak of winter, day 2 the peak of summer
+    fn four_day_year() -> SeasonSettings {
+        SeasonSettings {
+            days_per_year: 4,
+            start_day: 0,
+            summer_peak: 0.5,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn summer_factor_peaks_at_the_solstices() {
+        let settings = four_day_year();
+        // (day, summer factor)
+        let cases = [(0, 0.0), (1, 0.5), (2, 1.0), (3, 0.5), (4, 0.0), (6, 1.0)];
+        for (day, expected) in cases {
+            let factor = settings.summer_factor(day);
+            assert!(
+                (factor - expected).abs() < 1e-5,
+                "day {day}: {factor} != {expected}"
+            );
+        }
+        let shifted = SeasonSettings {
+            start_day: 2,
+            ..four_day_year()
+        };
+        assert!((shifted.summer_factor(0) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn time_settings_lerp_winter_to_summer() {
+        let settings = four_day_year();
+        assert!(settings.time_settings(0) == settings.winter_time_settings);
+        assert!(settings.time_settings(2) == settings.summer_time_settings);
+
+        let equinox = settings.time_settings(1);
+        let (w, s) = (
+            &settings.winter_time_settings,
+            &settings.summer_time_settings,
+        );
+        assert!((equinox.day_time_sec - (w.day_time_sec + s.day_time_sec) * 0.5).abs() < 1e-4);
+        assert!(
+            (equinox.night_time_sec - (w.night_time_sec + s.night_time_sec) * 0.5).abs() < 1e-4
+        );
+        assert!(
+            (equinox.sunrise_time_sec - (w.sunrise_time_sec + s.sunrise_time_sec) * 0.5).abs()
+                < 1e-4
+        );
+    }
+
+    #[test]
+    fn colors_need_both_palettes() {
+        let mut settings = four_day_year();
+        assert!(settings.colors(0).is_none());
+        settings.summer_colors = Some(SkyGradientBuilder::default());
+        assert!(settings.colors(0).is_none());
+        settings.summer_colors = None;
+        settings.winter_colors = Some(SkyGradientBuilder::default());
+        assert!(settings.colors(0).is_none());
+        settings.summer_colors = Some(SkyGradientBuilder::default());
+        assert!(settings.colors(0).is_some());
+    }
+
+    #[test]
+    fn new_day_updates_timings_and_sun() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(SkyTime::default())
+            .insert_resource(SkyTimeSettings::default())
+            .insert_resource(SunPath::Astronomical(AstronomicalSunSettings::default()))
+            .add_plugins(SeasonPlugin {
+                season_settings: four_day_year(),
+            });
+        let day_of_year = |app: &App| match app.world().resource::<SunPath>() {
+            SunPath::Astronomical(astronomical) => astronomical.day_of_year,
+            SunPath::Simple => unreachable!(),
+        };
+
+        app.update();
+        let settings = four_day_year();
+        assert!(*app.world().resource::<SkyTimeSettings>() == settings.winter_time_settings);
+        assert_eq!(day_of_year(&app), 0.0);
+
+        app.world_mut().resource_mut::<SkyTime>().day = 2;
+        app.update();
+        assert!(*app.world().resource::<SkyTimeSettings>() == settings.summer_time_settings);
+        assert!((day_of_year(&app) - 365.0 * 0.5).abs() < 1e-3);
+
+        // SeasonSettings owns the timings, edits are overwritten
+        app.world_mut()
+            .resource_mut::<SkyTimeSettings>()
+            .day_time_sec = 1.0;
+        app.update();
+        assert!(*app.world().resource::<SkyTimeSettings>() == settings.summer_time_settings);
+    }
+}
@@ -2,9 +2,15 @@ use bevy::mesh::MeshVertexBufferLayoutRef;
 use bevy::pbr::MaterialPipeline;
 use bevy::prelude::*;
 use bevy::render::render_resource::{AsBindGroup, CompareFunction, RenderPipelineDescriptor};
+use bevy::render::storage::ShaderStorageBuffer;
 use bevy::shader::ShaderRef;
 
-use crate::bind_groups::{StarsBindGroup, SunBindGroup};
+use crate::bind_groups::{
+    GpuGradientStop, GroundBindGroup, HorizonGlowBindGroup, StarsBindGroup, SunBindGroup,
+};
+use crate::gradient_material::FullGradientMaterial;
+use crate::instance::SkyInstanceOf;
+use crate::plugin::DefaultSkyFilter;
 
 #[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
 pub struct FullSkyMaterial {
@@ -31,6 +37,9 @@ pub struct FullSkyMaterial {
     pub feature_sun_enabled: i32,
     #[uniform(6)]
     pub feature_aurora_enabled: i32,
+    /// auto set from [`crate::plugin::SkySettings::dither_strength`]
+    #[uniform(7)]
+    pub dither_strength: f32,
 
     // noise
     #[texture(10, dimension = "3d")]
@@ -46,6 +55,63 @@ pub struct FullSkyMaterial {
     #[texture(16, dimension = "2d")]
     #[sampler(17)]
     pub gradient_image: Handle<Image>,
+    /// added on top of the gradient, not part of the gradient texture.
+    /// sun direction auto set by [`crate::sun::SunDriverPlugin`], see [`crate::sun::SunSettings::horizon_glow`]
+    #[uniform(23)]
+    pub horizon_glow: HorizonGlowBindGroup,
+
+    /// 1 = evaluate the gradient in this shader, instead of sampling `gradient_image`.
+    /// the fields below are copied from the default [`FullGradientMaterial`],
+    /// see [`crate::plugin::SkySettings::inline_gradient`]
+    #[uniform(18)]
+    pub feature_inline_gradient: i32,
+    #[storage(19, read_only)]
+    pub gradient_stops_buffer: Handle<ShaderStorageBuffer>,
+    /// the lut reads the cycle percent from the MeshTag of the skybox
+    #[uniform(20)]
+    pub gradient_lut_enabled: i32,
+    #[texture(21, dimension = "2d")]
+    #[sampler(22)]
+    pub gradient_lut_image: Option<Handle<Image>>,
+    #[storage(24, read_only)]
+    pub ground_stops_buffer: Handle<ShaderStorageBuffer>,
+    #[uniform(25)]
+    pub feature_ground_enabled: i32,
+    #[uniform(26)]
+    pub ground: GroundBindGroup,
+}
+
+impl FullSkyMaterial {
+    /// evaluate the gradient of `gradient` in the sky shader, sharing its buffers and lut.
+    /// only the fields that differ are copied
+    pub fn copy_inline_gradient(&mut self, gradient: &FullGradientMaterial) {
+        self.feature_inline_gradient = 1;
+        if self.gradient_stops_buffer != gradient.stops_buffer {
+            self.gradient_stops_buffer = gradient.stops_buffer.clone();
+        }
+        self.gradient_lut_enabled = gradient.feature_lut_enabled;
+        if self.gradient_lut_image != gradient.lut_image {
+            self.gradient_lut_image = gradient.lut_image.clone();
+        }
+        if self.ground_stops_buffer != gradient.ground_stops_buffer {
+            self.ground_stops_buffer = gradient.ground_stops_buffer.clone();
+        }
+        self.feature_ground_enabled = gradient.feature_ground_enabled;
+        if self.ground != gradient.ground {
+            self.ground.clone_from(&gradient.ground);
+        }
+    }
+
+    /// true if [`Self::copy_inline_gradient`] would change anything
+    pub fn inline_gradient_differs(&self, gradient: &FullGradientMaterial) -> bool {
+        self.feature_inline_gradient != 1
+            || self.gradient_stops_buffer != gradient.stops_buffer
+            || self.gradient_lut_enabled != gradient.feature_lut_enabled
+            || self.gradient_lut_image != gradient.lut_image
+            || self.ground_stops_buffer != gradient.ground_stops_buffer
+            || self.feature_ground_enabled != gradient.feature_ground_enabled
+            || self.ground != gradient.ground
+    }
 }
 
 impl Material for FullSkyMaterial {
@@ -90,6 +156,87 @@ impl Default for FullSkyMaterial {
             feature_stars_enabled: 1,
             feature_sun_enabled: 1,
             feature_aurora_enabled: 1,
+            dither_strength: 0.0,
+            feature_inline_gradient: 0,
+            gradient_stops_buffer: Handle::default(),
+            gradient_lut_enabled: 0,
+            gradient_lut_image: None,
+            horizon_glow: HorizonGlowBindGroup::default(),
+            ground_stops_buffer: Handle::default(),
+            feature_ground_enabled: 0,
+            ground: GroundBindGroup::default(),
+        }
+    }
+}
+
+// the storage buffers must exist, even when the gradient isn't inlined
+pub(crate) fn init_inline_gradient_buffers(
+    mut events: MessageReader<AssetEvent<FullSkyMaterial>>,
+    mut materials: ResMut<Assets<FullSkyMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut placeholder: Local<Option<Handle<ShaderStorageBuffer>>>,
+) {
+    for event in events.read() {
+        let AssetEvent::Added { id } = *event else {
+            continue;
+        };
+        let Some(material) = materials.get(id) else {
+            continue;
+        };
+        let stops_missing = !buffers.contains(&material.gradient_stops_buffer);
+        let ground_missing = !buffers.contains(&material.ground_stops_buffer);
+        if !stops_missing && !ground_missing {
+            continue;
+        }
+        let placeholder = placeholder
+            .get_or_insert_with(|| {
+                buffers.add(ShaderStorageBuffer::from(vec![GpuGradientStop {
+                    color: Vec4::ZERO,
+                    position: 0.0,
+                }]))
+            })
+            .clone();
+        let Some(material) = materials.get_mut(id) else {
+            continue;
+        };
+        if stops_missing {
+            material.gradient_stops_buffer = placeholder.clone();
+        }
+        if ground_missing {
+            material.ground_stops_buffer = placeholder;
+        }
+    }
+}
+
+// copy the default gradient into the default skybox, whenever either of them changes.
+// only added with SkySettings::inline_gradient
+pub(crate) fn sync_inline_gradient(
+    mut events: MessageReader<AssetEvent<FullGradientMaterial>>,
+    gradient_meshes: Query<&MeshMaterial3d<FullGradientMaterial>, DefaultSkyFilter>,
+    skyboxes: Query<&MeshMaterial3d<FullSkyMaterial>, Without<SkyInstanceOf>>,
+    gradient_materials: Res<Assets<FullGradientMaterial>>,
+    mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
+) {
+    let Ok(gradient_handle) = gradient_meshes.single() else {
+        events.clear();
+        return;
+    };
+    let gradient_changed = events.read().fold(false, |changed, event| {
+        changed || event.is_modified(gradient_handle) || event.is_added(gradient_handle)
+    });
+    let Some(gradient_material) = gradient_materials.get(gradient_handle) else {
+        return;
+    };
+    for skybox in &skyboxes {
+        let differs = sky_materials
+            .get(skybox)
+            .is_some_and(|sky_material| sky_material.inline_gradient_differs(gradient_material));
+        // new stops are written into the same buffers, the skybox still has to rebind them
+        if !differs && !gradient_changed {
+            continue;
+        }
+        if let Some(sky_material) = sky_materials.get_mut(skybox) {
+            sky_material.copy_inline_gradient(gradient_material);
         }
     }
 }
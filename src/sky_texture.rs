@@ -1,13 +1,15 @@
 use bevy::{
     asset::RenderAssetUsages,
-    camera::visibility::RenderLayers,
+    camera::{Viewport, visibility::RenderLayers},
     image::ImageSampler,
     prelude::*,
     render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
-    window::WindowResized,
 };
 
-use crate::plugin::SkyboxMagnetTag;
+use crate::{
+    plugin::{PrimaryMagnetFilter, SkySettings, SkyboxMagnetTag},
+    utils,
+};
 
 #[derive(Resource)]
 pub struct FullSkyTextureHandle {
@@ -58,6 +60,10 @@ impl Default for SkyTexturePlugin {
 #[derive(Component)]
 pub struct FullSkySpriteTag;
 
+/// tag to find the camera that draws the sky texture onto the screen
+#[derive(Component)]
+pub struct FullSkyScreenCameraTag;
+
 impl Plugin for SkyTexturePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(self.settings.clone());
@@ -74,7 +80,7 @@ impl Plugin for SkyTexturePlugin {
             PostUpdate,
             (
                 full_sky_camera_follow_primary.before(TransformSystems::Propagate),
-                resize_full_sky_on_window_change,
+                resize_full_sky_to_camera,
             ),
         );
     }
@@ -84,6 +90,7 @@ impl Plugin for SkyTexturePlugin {
 pub fn spawn_full_sky_texture(
     mut images: ResMut<Assets<Image>>,
     mut texture_handle: ResMut<FullSkyTextureHandle>,
+    sky_settings: Res<SkySettings>,
     // Use the window to get an initial size, full-resolution is best for the sky.
     primary_windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
 ) {
@@ -96,13 +103,23 @@ pub fn spawn_full_sky_texture(
         ..default()
     };
 
-    let mut sky_image = Image::new_fill(
-        size,
-        TextureDimension::D2,
-        &[0, 0, 0, 0],                 // Start with a black/clear texture
-        TextureFormat::Rgba8UnormSrgb, // Use RGBA for the final sky color
-        RenderAssetUsages::default(),
-    );
+    let mut sky_image = if sky_settings.hdr {
+        Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0; 8], // Start with a black/clear texture, 4 x f16
+            TextureFormat::Rgba16Float,
+            RenderAssetUsages::default(),
+        )
+    } else {
+        Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0, 0, 0, 0],                 // Start with a black/clear texture
+            TextureFormat::Rgba8UnormSrgb, // Use RGBA for the final sky color
+            RenderAssetUsages::default(),
+        )
+    };
     sky_image.sampler = ImageSampler::linear();
     sky_image.texture_descriptor.usage =
         TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
@@ -110,68 +127,103 @@ pub fn spawn_full_sky_texture(
     texture_handle.render_target = images.add(sky_image);
 }
 
-// System to resize the full sky texture when the window changes
-fn resize_full_sky_on_window_change(
-    mut resize_events: MessageReader<WindowResized>,
+// the sky texture matches what the primary SkyboxMagnetTag camera renders:
+// its viewport, or its whole window or image
+fn resize_full_sky_to_camera(
     mut images: ResMut<Assets<Image>>,
     sky_handles: Res<FullSkyTextureHandle>,
-    primary_windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    magnet_cameras: Query<&Camera, PrimaryMagnetFilter>,
 ) {
-    let mut update_texture = false;
-    for event in resize_events.read() {
-        if primary_windows.get(event.window).is_ok() {
-            update_texture = true;
-            break;
-        }
-    }
-    if !update_texture {
-        return;
-    }
-
-    let Ok(window) = primary_windows.single() else {
+    let Some(size) = magnet_cameras
+        .iter()
+        .next()
+        .and_then(Camera::physical_viewport_size)
+    else {
         return;
     };
-    let width = window.width() as u32;
-    let height = window.height() as u32;
-
-    if let Some(image) = images.get_mut(&sky_handles.render_target) {
-        image.resize(Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        });
-    }
+    utils::resize_render_target(&mut images, &sky_handles.render_target, size);
 }
 
 fn spawn_full_sky_camera(
     mut commands: Commands,
     full_sky_handle: Res<FullSkyTextureHandle>,
     settings: Res<SkyTexturePluginSettings>,
+    sky_settings: Res<SkySettings>,
 ) {
     // draw the full sky to a texture, instead of drawing directly to screen
-    commands.spawn((
-        Name::new("camera_full_sky"),
-        Camera3d::default(),
-        FullSkyCameraTag,
-        Camera {
-            order: settings.full_sky_camera_order,
-            target: full_sky_handle.render_target.clone().into(),
-            clear_color: ClearColorConfig::Custom(Color::NONE),
-            ..default()
-        },
-        Transform::default(),
-        settings.sky_render_layer.clone(), // The camera also needs the render layer
-    ));
+    commands
+        .spawn((
+            Name::new("camera_full_sky"),
+            Camera3d::default(),
+            FullSkyCameraTag,
+            Camera {
+                order: settings.full_sky_camera_order,
+                target: full_sky_handle.render_target.clone().into(),
+                clear_color: ClearColorConfig::Custom(Color::NONE),
+                ..default()
+            },
+            Transform::default(),
+            settings.sky_render_layer.clone(), // The camera also needs the render layer
+        ))
+        .insert_if(utils::hdr_render_target_camera(), || sky_settings.hdr);
 }
 
+type MagnetCameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Transform, &'static Projection, &'static Camera),
+    (PrimaryMagnetFilter, Without<FullSkyCameraTag>),
+>;
+type FullSkyCameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Transform, &'static mut Projection),
+    (With<FullSkyCameraTag>, Without<SkyboxMagnetTag>),
+>;
+
 fn full_sky_camera_follow_primary(
-    primary_cameras: Query<&Transform, (With<SkyboxMagnetTag>, Without<FullSkyCameraTag>)>,
-    mut sky_cameras: Query<&mut Transform, (With<FullSkyCameraTag>, Without<SkyboxMagnetTag>)>,
+    primary_cameras: MagnetCameraQuery,
+    mut sky_cameras: FullSkyCameraQuery,
+    mut screen_cameras: Query<
+        &mut Camera,
+        (With<FullSkyScreenCameraTag>, Without<SkyboxMagnetTag>),
+    >,
 ) {
-    if let Some(cam_tf) = primary_cameras.iter().next() {
-        for mut sky_tf in sky_cameras.iter_mut() {
-            *sky_tf = *cam_tf;
+    let Some((cam_tf, cam_projection, camera)) = primary_cameras.iter().next() else {
+        return;
+    };
+    for (mut sky_tf, mut sky_projection) in sky_cameras.iter_mut() {
+        sky_tf.set_if_neq(*cam_tf);
+        // Projection has no PartialEq, compare what it renders with
+        if !same_projection(&sky_projection, cam_projection) {
+            *sky_projection = cam_projection.clone();
+        }
+    }
+    // draw the sky into the same window, image and viewport as the magnet camera
+    for mut screen_camera in screen_cameras.iter_mut() {
+        // only write on change, a changed camera recomputes its projection
+        if screen_camera.target.normalize(None) != camera.target.normalize(None) {
+            screen_camera.target = camera.target.clone();
+        }
+        if !same_viewport(screen_camera.viewport.as_ref(), camera.viewport.as_ref()) {
+            screen_camera.viewport = camera.viewport.clone();
+        }
+    }
+}
+
+fn same_projection(a: &Projection, b: &Projection) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+        && a.get_clip_from_view() == b.get_clip_from_view()
+}
+
+fn same_viewport(a: Option<&Viewport>, b: Option<&Viewport>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.physical_position == b.physical_position
+                && a.physical_size == b.physical_size
+                && a.depth == b.depth
         }
+        (a, b) => a.is_none() && b.is_none(),
     }
 }
 
@@ -196,6 +248,7 @@ fn spawn_full_sky_screen_quad(
     commands.spawn((
         Name::new("Camera_fullsky_screenquad"),
         Camera2d,
+        FullSkyScreenCameraTag,
         Camera {
             order: settings.final_camera_order,
             clear_color: ClearColorConfig::Custom(Color::BLACK),
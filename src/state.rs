@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ambient_driver::{AmbientColorsBuilder, AmbientSettings},
+    cycle::{SkyTime, SkyTimeSettings, SkyTimeWarp},
+    noise::NoiseSettings,
+    presets::{ApplyPresetEvent, SkyPreset},
+    season::SeasonSettings,
+    sun::SunPath,
+    time_source::SkyTimeSource,
+};
+
+/// everything needed to save the sky, and reload it exactly.
+/// values belonging to disabled plugins are None, and are skipped when restoring
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SkyState {
+    pub sky_time: Option<SkyTime>,
+    pub sky_time_settings: Option<SkyTimeSettings>,
+    pub sky_time_warp: Option<SkyTimeWarp>,
+    /// the current look of the sky: colors, sun, stars and aurora
+    pub preset: SkyPreset,
+    pub ambient_colors_builder: Option<AmbientColorsBuilder>,
+    pub ambient_settings: Option<AmbientSettings>,
+    pub noise_settings: Option<NoiseSettings>,
+    #[serde(default)]
+    pub sun_path: Option<SunPath>,
+    #[serde(default)]
+    pub season_settings: Option<SeasonSettings>,
+    #[serde(default)]
+    pub time_source: Option<SkyTimeSource>,
+}
+
+impl SkyState {
+    /// snapshot the sky, call from an exclusive system or with direct World access
+    pub fn capture(world: &World) -> Self {
+        Self {
+            sky_time: world.get_resource::<SkyTime>().cloned(),
+            sky_time_settings: world.get_resource::<SkyTimeSettings>().cloned(),
+            sky_time_warp: world.get_resource::<SkyTimeWarp>().cloned(),
+            preset: SkyPreset::capture(world),
+            ambient_colors_builder: world.get_resource::<AmbientColorsBuilder>().cloned(),
+            ambient_settings: world.get_resource::<AmbientSettings>().cloned(),
+            noise_settings: world.get_resource::<NoiseSettings>().cloned(),
+            sun_path: world.get_resource::<SunPath>().cloned(),
+            season_settings: world.get_resource::<SeasonSettings>().cloned(),
+            time_source: world.get_resource::<SkyTimeSource>().cloned(),
+        }
+    }
+
+    /// overwrite the sky with this state.
+    /// resources are replaced immediately, the preset is applied through [`ApplyPresetEvent`].
+    /// the noise textures are rebuilt from the restored NoiseSettings
+    pub fn restore(&self, world: &mut World) {
+        restore_resource(world, &self.sky_time);
+        restore_resource(world, &self.sky_time_settings);
+        restore_resource(world, &self.sky_time_warp);
+        restore_resource(world, &self.ambient_colors_builder);
+        restore_resource(world, &self.ambient_settings);
+        restore_resource(world, &self.sun_path);
+        restore_resource(world, &self.season_settings);
+        restore_resource(world, &self.time_source);
+        if restore_resource(world, &self.noise_settings)
+            && let Some(mut noise_settings) = world.get_resource_mut::<NoiseSettings>()
+        {
+            // rebuild even if the restored settings equal the current ones
+            noise_settings.set_changed();
+        }
+        world.write_message(ApplyPresetEvent {
+            sky_preset: self.preset.clone(),
+        });
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::de::from_str(ron)
+    }
+}
+
+// only overwrite resources that exist, so disabled plugins stay disabled
+fn restore_resource<R: Resource + Clone>(world: &mut World, value: &Option<R>) -> bool {
+    if let Some(value) = value
+        && let Some(mut resource) = world.get_resource_mut::<R>()
+    {
+        *resource = value.clone();
+        return true;
+    }
+    false
+}
@@ -3,7 +3,9 @@ use std::f32::consts::PI;
 use bevy::{color::palettes::css::WHITE, light::light_consts::lux::AMBIENT_DAYLIGHT, prelude::*};
 
 use crate::{
-    cycle::{SkyTime, SkyTimeSettings},
+    bind_groups::HorizonGlowBindGroup,
+    cycle::{SkyTime, SkyTimeSettings, SkyTimeWarp},
+    plugin::DefaultSkyFilter,
     sky_material::FullSkyMaterial,
 };
 
@@ -21,6 +23,9 @@ pub struct SunSettings {
     pub sun_color: Vec4,
     pub sun_strength: f32,
     pub sun_sharpness: f32,
+    /// glow added to the sky around the sun, near the horizon. `sun_dir` is auto set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub horizon_glow: HorizonGlowBindGroup,
 }
 
 impl Default for SunSettings {
@@ -30,16 +35,182 @@ impl Default for SunSettings {
             sun_color: Vec4::new(1.0, 1.0, 0.5, 1.0),
             sun_strength: 1.5,
             sun_sharpness: 364.0,
+            horizon_glow: HorizonGlowBindGroup::default(),
         }
     }
 }
 
+impl SunSettings {
+    /// the horizon glow, pointed at the sun
+    pub fn horizon_glow_towards(&self, sun_dir: Vec3) -> HorizonGlowBindGroup {
+        HorizonGlowBindGroup {
+            sun_dir,
+            ..self.horizon_glow.clone()
+        }
+    }
+}
+
+/// how the sun travels across the sky
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Reflect, Clone, Default)]
+pub enum SunPath {
+    /// spins around the X axis, rises due east and passes straight overhead
+    #[default]
+    Simple,
+    /// sun direction is calculated from latitude, date and axial tilt
+    Astronomical(AstronomicalSunSettings),
+}
+
+impl SunPath {
+    /// direction pointing at the sun, and the rotation of a light shining away from the sun
+    pub fn sun_orientation(&self, sky_time_settings: &SkyTimeSettings, time: f32) -> (Vec3, Quat) {
+        match self {
+            SunPath::Simple => {
+                let time_rotation = sky_time_settings.time_2pi(time);
+                // this rotation is looking at the sun
+                let rotation_to_sun =
+                    Quat::from_rotation_x(time_rotation.sin().atan2(time_rotation.cos()));
+                (
+                    rotation_to_sun * Vec3::NEG_Z,
+                    rotation_to_sun * Quat::from_rotation_x(PI),
+                )
+            }
+            SunPath::Astronomical(astronomical) => {
+                let hours = sky_time_settings.hours(time);
+                let look_at_sun = astronomical.sun_direction(hours);
+                // avoid a degenerate up vector when the sun is straight overhead
+                let up = if look_at_sun.y.abs() > 0.999 {
+                    Vec3::NEG_Z
+                } else {
+                    Vec3::Y
+                };
+                let look_away_sun = Transform::default().looking_to(-look_at_sun, up).rotation;
+                (look_at_sun, look_away_sun)
+            }
+        }
+    }
+
+    /// the sky time the gradients, ambient colors and stars are sampled at.
+    /// Simple returns `time` as is. Astronomical remaps it, so sunrise and sunset colors show
+    /// when the sun actually crosses the horizon, instead of at [`crate::cycle::SUNRISE_HOUR`] and [`crate::cycle::SUNSET_HOUR`].
+    /// `SkyTime`, [`SkyTimeSettings::hours`] and the [`crate::cycle::SkyPhaseChanged`] events still follow the clock
+    pub fn gradient_time(&self, sky_time_settings: &SkyTimeSettings, time: f32) -> f32 {
+        let SunPath::Astronomical(astronomical) = self else {
+            return time;
+        };
+        let hours = sky_time_settings.hours(time);
+        let percent = match astronomical.sunrise_sunset_hours() {
+            Some((sunrise, sunset)) => {
+                let day_length = (sunset - sunrise).rem_euclid(24.0);
+                let since_sunrise = (hours - sunrise).rem_euclid(24.0);
+                if since_sunrise < day_length {
+                    0.5 * since_sunrise / day_length
+                } else {
+                    0.5 + 0.5 * (since_sunrise - day_length) / (24.0 - day_length)
+                }
+            }
+            // midnight sun: noon colors all day. polar night: midnight colors
+            None if astronomical
+                .sun_direction(astronomical.solar_noon_hours())
+                .y
+                > 0.0 =>
+            {
+                0.25
+            }
+            None => 0.75,
+        };
+        sky_time_settings.time_from_percent(percent)
+    }
+}
+
+/// observer location and date used by [`SunPath::Astronomical`]
+/// the wall-clock time from [`SkyTimeSettings::hours`] is used as the local clock time
+/// world directions: east = -Z, west = +Z, north = -X, south = +X, up = +Y
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Reflect, Clone)]
+pub struct AstronomicalSunSettings {
+    /// degrees, -90 (south pole) -> 90 (north pole)
+    pub latitude: f32,
+    /// degrees, -180 (west) -> 180 (east)
+    pub longitude: f32,
+    /// degrees, the longitude the local clock is based upon. 15 degrees per hour from UTC
+    /// set equal to `longitude` to have the sun peak exactly at 12:00
+    pub time_zone_meridian: f32,
+    /// 0.0 -> 365.0, day 0 is january 1st
+    pub day_of_year: f32,
+    /// degrees, earth's axial tilt is 23.44
+    pub axial_tilt: f32,
+}
+
+impl Default for AstronomicalSunSettings {
+    fn default() -> Self {
+        Self {
+            latitude: 45.0,
+            longitude: 0.0,
+            time_zone_meridian: 0.0,
+            // spring equinox
+            day_of_year: 79.0,
+            axial_tilt: 23.44,
+        }
+    }
+}
+
+impl AstronomicalSunSettings {
+    /// solar declination in radians, approximated as a cosine over the year
+    pub fn declination(&self) -> f32 {
+        let year_angle = 2.0 * PI * (self.day_of_year + 10.0) / 365.0;
+        -self.axial_tilt.to_radians() * year_angle.cos()
+    }
+
+    /// hour angle in radians, 0 when the sun is at its highest
+    pub fn hour_angle(&self, clock_hours: f32) -> f32 {
+        let solar_hours = clock_hours + (self.longitude - self.time_zone_meridian) / 15.0;
+        (solar_hours - 12.0) * 15.0_f32.to_radians()
+    }
+
+    /// wall-clock hours (0.0 -> 24.0) where the sun is at its highest
+    pub fn solar_noon_hours(&self) -> f32 {
+        (12.0 - (self.longitude - self.time_zone_meridian) / 15.0).rem_euclid(24.0)
+    }
+
+    /// wall-clock hours (0.0 -> 24.0) of sunrise and sunset, where the sun crosses the horizon.
+    /// None when the sun never rises or never sets that day, near the poles
+    pub fn sunrise_sunset_hours(&self) -> Option<(f32, f32)> {
+        let cos_hour_angle = -self.latitude.to_radians().tan() * self.declination().tan();
+        if !(-1.0..1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+        let half_day_hours = cos_hour_angle.acos().to_degrees() / 15.0;
+        let noon = self.solar_noon_hours();
+        Some((
+            (noon - half_day_hours).rem_euclid(24.0),
+            (noon + half_day_hours).rem_euclid(24.0),
+        ))
+    }
+
+    /// normalized direction pointing at the sun, for the given wall-clock hours (0.0 -> 24.0)
+    pub fn sun_direction(&self, clock_hours: f32) -> Vec3 {
+        let latitude = self.latitude.to_radians();
+        let declination = self.declination();
+        let hour_angle = self.hour_angle(clock_hours);
+
+        let east = -declination.cos() * hour_angle.sin();
+        let north = latitude.cos() * declination.sin()
+            - latitude.sin() * declination.cos() * hour_angle.cos();
+        let up = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+
+        (Vec3::NEG_Z * east + Vec3::NEG_X * north + Vec3::Y * up).normalize()
+    }
+}
+
 /// "Drives" a sun light source
 /// and updates the sun values of full_sky material
 #[derive(Clone)]
 pub struct SunDriverPlugin {
     pub spawn_default_sun_light: bool,
     pub sun_settings: SunSettings,
+    pub sun_path: SunPath,
 }
 
 impl Default for SunDriverPlugin {
@@ -47,6 +218,7 @@ impl Default for SunDriverPlugin {
         Self {
             spawn_default_sun_light: true,
             sun_settings: SunSettings::default(),
+            sun_path: SunPath::default(),
         }
     }
 }
@@ -54,7 +226,9 @@ impl Default for SunDriverPlugin {
 impl Plugin for SunDriverPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<SunSettings>();
+        app.register_type::<SunPath>();
         app.insert_resource(self.sun_settings.clone());
+        app.insert_resource(self.sun_path.clone());
         app.add_systems(PostUpdate, drive_sun);
         if self.spawn_default_sun_light {
             app.add_systems(Startup, spawn_default_sun);
@@ -75,22 +249,21 @@ fn spawn_default_sun(mut commands: Commands) {
     ));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn drive_sun(
     mut suns: Query<(&mut Transform, &mut DirectionalLight), With<SunDriverTag>>,
     sky_time_settings: Res<SkyTimeSettings>,
     sky_time: Res<SkyTime>,
+    sky_time_warp: Res<SkyTimeWarp>,
     sun_settings: Res<SunSettings>,
-    skyboxes: Query<&mut MeshMaterial3d<FullSkyMaterial>>,
+    sun_path: Res<SunPath>,
+    skyboxes: Query<&mut MeshMaterial3d<FullSkyMaterial>, DefaultSkyFilter>,
     mut sky_materials: ResMut<Assets<FullSkyMaterial>>,
 ) {
     // UPDATE the sun directional light
-    let time_rotation = sky_time_settings.time_2pi(sky_time.time);
-
-    // this rotation is looking at the sun
-    let rotation_to_sun = Quat::from_rotation_x(time_rotation.sin().atan2(time_rotation.cos()));
-    let look_at_sun = rotation_to_sun * Vec3::NEG_Z;
-    let look_away_sun = rotation_to_sun * Quat::from_rotation_x(PI);
-    let illuminance = time_rotation.sin().max(0.0).powf(2.0) * sun_settings.illuminance;
+    let time = sky_time_warp.warp_time(&sky_time_settings, sky_time.time);
+    let (look_at_sun, look_away_sun) = sun_path.sun_orientation(&sky_time_settings, time);
+    let illuminance = look_at_sun.y.max(0.0).powf(2.0) * sun_settings.illuminance;
 
     for (mut light_trans, mut directional) in suns.iter_mut() {
         light_trans.rotation = look_away_sun;
@@ -110,4 +283,68 @@ fn drive_sun(
     skybox_material.sun.sun_color = sun_settings.sun_color;
     skybox_material.sun.sun_strength = sun_settings.sun_strength;
     skybox_material.sun.sun_sharpness = sun_settings.sun_sharpness;
+    // the skybox changes every frame anyway, the gradient material can stay untouched
+    skybox_material.horizon_glow = sun_settings.horizon_glow_towards(look_at_sun);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn astronomical(latitude: f32, day_of_year: f32) -> AstronomicalSunSettings {
+        AstronomicalSunSettings {
+            latitude,
+            day_of_year,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn equinox_sun_rises_at_six() {
+        let (sunrise, sunset) = astronomical(45.0, 79.0).sunrise_sunset_hours().unwrap();
+        assert!((sunrise - 6.0).abs() < 0.1, "{sunrise}");
+        assert!((sunset - 18.0).abs() < 0.1, "{sunset}");
+    }
+
+    #[test]
+    fn summer_days_are_longer_up_north() {
+        let (sunrise, sunset) = astronomical(60.0, 172.0).sunrise_sunset_hours().unwrap();
+        assert!(sunrise < 4.0, "{sunrise}");
+        assert!(sunset > 20.0, "{sunset}");
+    }
+
+    #[test]
+    fn gradient_follows_the_horizon_crossings() {
+        let settings = SkyTimeSettings::default();
+        let sun = astronomical(60.0, 172.0);
+        let path = SunPath::Astronomical(sun.clone());
+        let (sunrise, sunset) = sun.sunrise_sunset_hours().unwrap();
+        let at = |hours: f32| {
+            let time = path.gradient_time(&settings, settings.time_from_hours(hours));
+            settings.time_percent(time)
+        };
+        // 0.0 and 1.0 are both sunrise
+        let cycle_distance = |a: f32, b: f32| (a - b).rem_euclid(1.0).min((b - a).rem_euclid(1.0));
+        assert!(cycle_distance(at(sunrise), 0.0) < 0.001);
+        assert!(cycle_distance(at(sunset), 0.5) < 0.001);
+        // the sun is still high at 18:00, the gradient should be far from sunset
+        assert!(at(18.0) < 0.45, "{}", at(18.0));
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        let settings = SkyTimeSettings::default();
+        let time = settings.time_from_hours(0.0);
+        let midnight_sun = SunPath::Astronomical(astronomical(80.0, 172.0));
+        let polar_night = SunPath::Astronomical(astronomical(80.0, 355.0));
+        let percent = |path: &SunPath| settings.time_percent(path.gradient_time(&settings, time));
+        assert_eq!(percent(&midnight_sun), 0.25);
+        assert_eq!(percent(&polar_night), 0.75);
+    }
+
+    #[test]
+    fn simple_path_keeps_the_time() {
+        let settings = SkyTimeSettings::default();
+        assert_eq!(SunPath::Simple.gradient_time(&settings, 12.5), 12.5);
+    }
 }
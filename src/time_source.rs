@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::cycle::{SkyTime, SkyTimeSettings};
+
+/// what advances SkyTime
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Resource, Reflect, Clone, Default, PartialEq)]
+pub enum SkyTimeSource {
+    /// advance by `Time::delta_secs` every frame
+    #[default]
+    Local,
+    /// advance in FixedUpdate by the fixed timestep.
+    /// every client with the same timestep ends up with the same sky time
+    Fixed,
+    /// advance locally, but continuously blend towards the latest [`SkyTimeSnapshot`]
+    /// received through [`SkyTimeAuthority::receive`]
+    Authoritative,
+}
+
+/// a replicable point in sky time
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Default)]
+pub struct SkyTimeSnapshot {
+    pub day: u32,
+    pub time: f32,
+}
+
+impl SkyTimeSnapshot {
+    pub fn capture(sky_time: &SkyTime) -> Self {
+        Self {
+            day: sky_time.day,
+            time: sky_time.time,
+        }
+    }
+
+    /// sky time from a server tick counter, calculated in f64 so long running servers don't lose precision
+    pub fn from_tick(tick: u64, ticks_per_sec: f64, sky_time_settings: &SkyTimeSettings) -> Self {
+        Self::from_elapsed_secs(tick as f64 / ticks_per_sec, sky_time_settings)
+    }
+
+    /// sky time from seconds since the sky started, such as a replicated server timestamp
+    pub fn from_elapsed_secs(elapsed_secs: f64, sky_time_settings: &SkyTimeSettings) -> Self {
+        let total_time = sky_time_settings.total_time() as f64;
+        if total_time <= 0.0 {
+            return Self::default();
+        }
+        let day = (elapsed_secs / total_time).floor();
+        Self {
+            day: day as u32,
+            time: (elapsed_secs - day * total_time) as f32,
+        }
+    }
+
+    /// how many seconds `self` is ahead of `other`.
+    /// the day difference is taken first, so large day counters don't lose precision
+    pub fn seconds_ahead_of(&self, other: &SkyTimeSnapshot, total_time: f32) -> f32 {
+        let days_ahead = self.day as i64 - other.day as i64;
+        (days_ahead as f64 * total_time as f64 + (self.time as f64 - other.time as f64)) as f32
+    }
+
+    pub fn write_to(&self, sky_time: &mut SkyTime) {
+        sky_time.day = self.day;
+        sky_time.time = self.time;
+    }
+}
+
+/// receives authoritative sky times, used by [`SkyTimeSource::Authoritative`]
+#[derive(Resource, Reflect, Clone)]
+pub struct SkyTimeAuthority {
+    /// the latest authoritative time, extrapolated every frame since it was received
+    pub target: Option<SkyTimeSnapshot>,
+    /// seconds it takes to blend out ~63% of a correction. higher = smoother, but slower
+    pub smoothing_sec: f32,
+    /// corrections larger than this many seconds snap instead of blending
+    pub snap_threshold_sec: f32,
+}
+
+impl Default for SkyTimeAuthority {
+    fn default() -> Self {
+        Self {
+            target: None,
+            smoothing_sec: 0.5,
+            snap_threshold_sec: 5.0,
+        }
+    }
+}
+
+impl SkyTimeAuthority {
+    /// call when a new time arrives from the server
+    pub fn receive(&mut self, snapshot: SkyTimeSnapshot) {
+        self.target = Some(snapshot);
+    }
+
+    /// extrapolate the target by `delta_secs`, and blend `sky_time` towards it.
+    /// corrections above `snap_threshold_sec` snap
+    pub fn step(
+        &mut self,
+        sky_time: &mut SkyTime,
+        sky_time_settings: &SkyTimeSettings,
+        delta_secs: f32,
+    ) {
+        let smoothing_sec = self.smoothing_sec;
+        let snap_threshold_sec = self.snap_threshold_sec;
+        let Some(target) = &mut self.target else {
+            return;
+        };
+        let total_time = sky_time_settings.total_time();
+        if total_time <= 0.0 {
+            return;
+        }
+
+        // the server keeps ticking after sending, extrapolate the same way we predict locally
+        if sky_time.auto_tick {
+            target.time += delta_secs * sky_time.speed;
+            while target.time > total_time {
+                target.time -= total_time;
+                target.day += 1;
+            }
+        }
+
+        let error = target.seconds_ahead_of(&SkyTimeSnapshot::capture(sky_time), total_time);
+        if error.abs() > snap_threshold_sec || smoothing_sec <= 0.0 {
+            target.write_to(sky_time);
+            return;
+        }
+
+        let correction = error * (1.0 - (-delta_secs / smoothing_sec).exp());
+        sky_time.time += correction;
+        sky_time.wrap(sky_time_settings);
+    }
+}
+
+/// blend the locally predicted SkyTime towards the authoritative time
+pub(crate) fn apply_sky_time_authority(
+    time: Res<Time>,
+    sky_time_settings: Res<SkyTimeSettings>,
+    mut sky_time: ResMut<SkyTime>,
+    mut authority: ResMut<SkyTimeAuthority>,
+) {
+    authority.step(&mut sky_time, &sky_time_settings, time.delta_secs());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(day: u32, time: f32) -> SkyTimeSnapshot {
+        SkyTimeSnapshot { day, time }
+    }
+
+    fn sky_time(day: u32, time: f32) -> SkyTime {
+        SkyTime {
+            day,
+            time,
+            // keep the target still, only the blending is tested
+            auto_tick: false,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn seconds_ahead_of() {
+        let total = SkyTimeSettings::default().total_time();
+        let cases = [
+            (snapshot(0, 10.0), snapshot(0, 5.0), 5.0),
+            (snapshot(0, 5.0), snapshot(0, 10.0), -5.0),
+            // across the day boundary, both ways
+            (snapshot(1, 0.1), snapshot(0, total - 0.1), 0.2),
+            (snapshot(0, total - 0.1), snapshot(1, 0.1), -0.2),
+            // far into the game, day * total_time would be too large for f32
+            (
+                snapshot(1_000_001, 0.1),
+                snapshot(1_000_000, total - 0.1),
+                0.2,
+            ),
+            (snapshot(3, 1.0), snapshot(1, 1.0), total * 2.0),
+        ];
+        for (a, b, expected) in cases {
+            let ahead = a.seconds_ahead_of(&b, total);
+            assert!((ahead - expected).abs() < 0.0001, "{a:?} {b:?}: {ahead}");
+        }
+    }
+
+    #[test]
+    fn blends_exponentially() {
+        let settings = SkyTimeSettings::default();
+        let mut authority = SkyTimeAuthority::default();
+        authority.receive(snapshot(0, 11.0));
+        let mut local = sky_time(0, 10.0);
+        // one smoothing period removes ~63% of the error
+        authority.step(&mut local, &settings, authority.smoothing_sec);
+        assert!((local.time - (10.0 + 1.0 - (-1.0f32).exp())).abs() < 0.0001);
+        // and it keeps closing in
+        for _ in 0..20 {
+            authority.step(&mut local, &settings, authority.smoothing_sec);
+        }
+        assert!((local.time - 11.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn snaps_large_corrections() {
+        let settings = SkyTimeSettings::default();
+        let mut authority = SkyTimeAuthority::default();
+        authority.receive(snapshot(2, 20.0));
+        let mut local = sky_time(2, 10.0);
+        authority.step(&mut local, &settings, 0.016);
+        assert_eq!((local.day, local.time), (2, 20.0));
+
+        authority.smoothing_sec = 0.0;
+        authority.receive(snapshot(2, 21.0));
+        authority.step(&mut local, &settings, 0.016);
+        assert_eq!((local.day, local.time), (2, 21.0));
+    }
+
+    #[test]
+    fn blends_across_the_day_boundary() {
+        let settings = SkyTimeSettings::default();
+        let total = settings.total_time();
+
+        // server already in the next day, client still at the end of the previous one
+        let mut authority = SkyTimeAuthority::default();
+        authority.receive(snapshot(5, 0.1));
+        let mut local = sky_time(4, total - 0.1);
+        for _ in 0..50 {
+            authority.step(&mut local, &settings, 0.1);
+        }
+        assert_eq!(local.day, 5);
+        assert!((local.time - 0.1).abs() < 0.001, "{}", local.time);
+
+        // client ahead, already wrapped, the server is still in the previous day
+        authority.receive(snapshot(4, total - 0.1));
+        let mut local = sky_time(5, 0.1);
+        for _ in 0..50 {
+            authority.step(&mut local, &settings, 0.1);
+        }
+        assert_eq!(local.day, 4);
+        assert!((local.time - (total - 0.1)).abs() < 0.001, "{}", local.time);
+    }
+}
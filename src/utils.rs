@@ -1,6 +1,14 @@
 use bevy::{
-    mesh::{Indices, VertexAttributeValues},
+    asset::RenderAssetUsages,
+    camera::visibility::RenderLayers,
+    core_pipeline::tonemapping::Tonemapping,
+    image::ImageSampler,
+    mesh::{Indices, MeshTag, VertexAttributeValues},
     prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::Hdr,
+    },
 };
 
 pub fn default_sky_mesh() -> Mesh {
@@ -9,6 +17,159 @@ pub fn default_sky_mesh() -> Mesh {
     mesh
 }
 
+/// a blank 2x2 texture that a camera can render into, resized later to match the screen
+/// see [`crate::plugin::SkySettings::render_target_format`]
+pub fn render_target_image(format: TextureFormat) -> Image {
+    let size = Extent3d {
+        width: 2,
+        height: 2,
+        ..default()
+    };
+
+    let pixel_size = format.block_copy_size(None).unwrap_or(4) as usize;
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &vec![0; pixel_size],
+        format,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::linear();
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+/// resize a render target to `size` pixels, only if it changed
+pub fn resize_render_target(images: &mut Assets<Image>, handle: &Handle<Image>, size: UVec2) {
+    let size = size.max(UVec2::splat(2));
+    if images.get(handle).is_some_and(|image| image.size() != size)
+        && let Some(image) = images.get_mut(handle)
+    {
+        image.resize(Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        });
+    }
+}
+
+/// for cameras rendering into an HDR sky texture.
+/// tonemapping is left to the camera that draws the sky on screen
+pub fn hdr_render_target_camera() -> (Hdr, Tonemapping) {
+    (Hdr, Tonemapping::None)
+}
+
+/// spawns the parts of a camera's own sky, shared by SkyInstance and MagnetSkyRig.
+/// every part gets `owner`, so the global sky systems skip it, and the rig can despawn it
+pub(crate) struct RigSpawner<'a, 'w, 's, O: Component + Clone> {
+    pub commands: &'a mut Commands<'w, 's>,
+    pub meshes: &'a mut Assets<Mesh>,
+    pub images: &'a mut Assets<Image>,
+    pub owner: O,
+    /// entity names start with this, like "sky_instance"
+    pub name: &'static str,
+    /// orders of every camera, including the rig cameras spawned so far, see [`camera_orders`]
+    pub taken_orders: &'a mut Vec<isize>,
+}
+
+/// the orders of all cameras, for [`RigSpawner::taken_orders`]
+pub(crate) fn camera_orders(cameras: &Query<&Camera>) -> Vec<isize> {
+    cameras.iter().map(|camera| camera.order).collect()
+}
+
+/// a sky mesh, rendered by its own camera into `texture`
+pub(crate) struct RigTexturePass {
+    pub mesh: Entity,
+    pub camera: Entity,
+    pub texture: Handle<Image>,
+}
+
+impl<O: Component + Clone> RigSpawner<'_, '_, '_, O> {
+    /// the skybox the rig camera sees, on `layer`
+    pub fn skybox<M: Material>(&mut self, material: Handle<M>, layer: usize) -> Entity {
+        self.commands
+            .spawn((
+                Name::new(format!("{}_skybox", self.name)),
+                self.owner.clone(),
+                Mesh3d(self.meshes.add(default_sky_mesh())),
+                MeshMaterial3d(material),
+                // the cycle percent, used by an inline GradientDriverMode::BakedLut
+                MeshTag::default(),
+                RenderLayers::layer(layer),
+            ))
+            .id()
+    }
+
+    /// `material` on a sky mesh, rendered by a camera into a new `format` texture.
+    /// the camera gets the first of `orders` no other camera uses, two cameras with the same order
+    /// render in an ambiguous order. both live on `layer` only, no other camera sees the mesh
+    pub fn texture_pass<M: Material>(
+        &mut self,
+        pass: &str,
+        material: Handle<M>,
+        layer: usize,
+        orders: impl IntoIterator<Item = isize>,
+        format: TextureFormat,
+        hdr: bool,
+    ) -> RigTexturePass {
+        let order = orders
+            .into_iter()
+            .find(|order| !self.taken_orders.contains(order))
+            .unwrap_or_default();
+        self.taken_orders.push(order);
+        let texture = self.images.add(render_target_image(format));
+        let mesh = self
+            .commands
+            .spawn((
+                Name::new(format!("{}_{pass}", self.name)),
+                self.owner.clone(),
+                Mesh3d(self.meshes.add(default_sky_mesh())),
+                MeshMaterial3d(material),
+                // the cycle percent, used by GradientDriverMode::BakedLut
+                MeshTag::default(),
+                RenderLayers::layer(layer),
+            ))
+            .id();
+        let camera = self
+            .commands
+            .spawn((
+                Name::new(format!("{}_camera_{pass}", self.name)),
+                self.owner.clone(),
+                Camera3d::default(),
+                Camera {
+                    order,
+                    target: texture.clone().into(),
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                    ..default()
+                },
+                Transform::default(),
+                RenderLayers::layer(layer),
+            ))
+            .insert_if(hdr_render_target_camera(), || hdr)
+            .id();
+        RigTexturePass {
+            mesh,
+            camera,
+            texture,
+        }
+    }
+}
+
+/// true for float formats, that keep colors above 1.0 and don't band
+pub fn is_float_format(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::R16Float
+            | TextureFormat::Rg16Float
+            | TextureFormat::Rgba16Float
+            | TextureFormat::R32Float
+            | TextureFormat::Rg32Float
+            | TextureFormat::Rgba32Float
+            | TextureFormat::Rg11b10Ufloat
+    )
+}
+
 pub fn flip_mesh_normals(mesh: &mut Mesh) {
     if let Some(normals) = mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL) {
         if let VertexAttributeValues::Float32x3(values) = normals {